@@ -0,0 +1,278 @@
+//! The walls around the play area, and the cameras looking at it.
+//!
+//! The camera is scaled so that the whole arena fits the window, whatever its size, and the space
+//! left over on either side is covered by letterbox bars.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collision::{Bounce, Collider, KillsBall, SpeedChange},
+    despawn::Despawns,
+    game::Name,
+    schedule::UI,
+};
+
+/// Builds the walls around the [`Arena`], and rebuilds them whenever it changes
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(ClearColor(Vec4::from(BACKGROUND_COLOR).into())) // the window's background colour
+            .init_resource::<ArenaMaterials>()
+            .add_resource(ArenaView { scale: 1.0 })
+            .add_startup_system(setup_arena.system())
+            .add_system(arena_walls_system.system())
+            // ahead of the HUD placing anything by the view, as the ArenaPlugin is added first
+            .add_system_to_stage(UI, camera_fit_system.system());
+    }
+}
+
+// const BACKGROUND_COLOR: Color = Color::rgb(0.7, 0.7, 0.7);
+const BACKGROUND_COLOR: [f32; 4] = [0.7, 0.7, 0.7, 0.0];
+
+/// Wider and taller than any window could show, in world units
+const LETTERBOX_SIZE: f32 = 10_000.0;
+
+/// The size of the play area, measured between the middles of the walls around it, which is
+/// centred on the origin. Each level can have its own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Arena {
+    pub width: f32,
+    pub height: f32,
+    pub wall_thickness: f32,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self {
+            width: 900.0,
+            height: 600.0,
+            wall_thickness: 10.0,
+        }
+    }
+}
+
+impl Arena {
+    /// How far the insides of the side walls are from the middle
+    pub fn inner_half_width(&self) -> f32 {
+        (self.width - self.wall_thickness) / 2.0
+    }
+
+    /// How far the insides of the top and bottom walls are from the middle
+    pub fn inner_half_height(&self) -> f32 {
+        (self.height - self.wall_thickness) / 2.0
+    }
+
+    /// The height of the paddle, a little way above the bottom wall
+    pub fn paddle_y(&self) -> f32 {
+        -self.inner_half_height() + 80.0
+    }
+}
+
+/// How the camera looks at the arena
+pub struct ArenaView {
+    /// World units to a window pixel
+    pub scale: f32,
+}
+
+impl ArenaView {
+    /// Where `position` in the world is in the window, measured from its top left corner, as UI
+    /// is positioned
+    pub fn to_window(&self, position: Vec3, window: &Window) -> Vec2 {
+        Vec2::new(
+            position.x() / self.scale + window.width() as f32 / 2.0,
+            window.height() as f32 / 2.0 - position.y() / self.scale,
+        )
+    }
+}
+
+/// Marks the walls built by the [`ArenaPlugin`], to clear them away when the arena changes
+pub struct Wall;
+
+/// Marks the bars covering the window outside the arena
+struct Letterbox;
+
+/// Marks the camera that looks at the arena, rather than the UI
+struct ArenaCamera;
+
+struct ArenaMaterials {
+    wall: Handle<ColorMaterial>,
+    letterbox: Handle<ColorMaterial>,
+}
+
+impl FromResources for ArenaMaterials {
+    fn from_resources(resources: &Resources) -> Self {
+        let mut materials = resources.get_mut::<Assets<ColorMaterial>>().unwrap();
+        ArenaMaterials {
+            wall: materials.add(Color::rgb(0.5, 0.5, 0.5).into()),
+            letterbox: materials.add(Color::rgb(0.1, 0.1, 0.1).into()),
+        }
+    }
+}
+
+fn setup_arena(mut commands: Commands) {
+    commands
+        // cameras
+        .spawn(Camera2dComponents::default())
+        .with(ArenaCamera)
+        .spawn(UiCameraComponents::default());
+}
+
+/// Scales the camera so that the arena and its walls just fit the window
+fn camera_fit_system(
+    windows: Res<Windows>,
+    arena: Res<Arena>,
+    mut view: ResMut<ArenaView>,
+    mut camera_query: Query<(&ArenaCamera, &mut Transform)>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    // minimised
+    if window.width() == 0 || window.height() == 0 {
+        return;
+    }
+    let scale = f32::max(
+        (arena.width + arena.wall_thickness) / window.width() as f32,
+        (arena.height + arena.wall_thickness) / window.height() as f32,
+    );
+    if scale == view.scale {
+        return;
+    }
+    view.scale = scale;
+    for (_camera, mut transform) in &mut camera_query.iter() {
+        transform.scale = Vec3::new(scale, scale, 1.0);
+    }
+}
+
+/// Builds the walls the first frame, and again whenever a level with a different arena starts
+fn arena_walls_system(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    arena_materials: Res<ArenaMaterials>,
+    mut built: Local<Option<Arena>>,
+    mut despawns: ResMut<Despawns>,
+    mut wall_query: Query<(Entity, &Wall)>,
+    mut letterbox_query: Query<(Entity, &Letterbox)>,
+) {
+    if built.as_ref() == Some(&*arena) {
+        return;
+    }
+    *built = Some(arena.clone());
+    for (entity, _wall) in &mut wall_query.iter() {
+        despawns.despawn(entity);
+    }
+    for (entity, _letterbox) in &mut letterbox_query.iter() {
+        despawns.despawn(entity);
+    }
+
+    let thickness = arena.wall_thickness;
+    let (width, height) = (arena.width, arena.height);
+    spawn_wall(
+        &mut commands,
+        arena_materials.wall.clone(),
+        Vec3::new(-width / 2.0, 0.0, 0.0),
+        Vec2::new(thickness, height + thickness),
+        "Left wall",
+    );
+    spawn_wall(
+        &mut commands,
+        arena_materials.wall.clone(),
+        Vec3::new(width / 2.0, 0.0, 0.0),
+        Vec2::new(thickness, height + thickness),
+        "Right wall",
+    );
+    spawn_wall(
+        &mut commands,
+        arena_materials.wall.clone(),
+        Vec3::new(0.0, height / 2.0, 0.0),
+        Vec2::new(width + thickness, thickness),
+        "Top wall",
+    );
+    spawn_bottom_wall(
+        &mut commands,
+        arena_materials.wall.clone(),
+        Vec3::new(0.0, -height / 2.0, 0.0),
+        Vec2::new(width + thickness, thickness),
+    );
+
+    // up against the outsides of the walls
+    let outer_half_width = (width + thickness) / 2.0;
+    let outer_half_height = (height + thickness) / 2.0;
+    let offset = LETTERBOX_SIZE / 2.0;
+    for &(translation, size) in [
+        (
+            Vec3::new(-outer_half_width - offset, 0.0, 0.0),
+            Vec2::new(LETTERBOX_SIZE, 2.0 * LETTERBOX_SIZE),
+        ),
+        (
+            Vec3::new(outer_half_width + offset, 0.0, 0.0),
+            Vec2::new(LETTERBOX_SIZE, 2.0 * LETTERBOX_SIZE),
+        ),
+        (
+            Vec3::new(0.0, outer_half_height + offset, 0.0),
+            Vec2::new(2.0 * LETTERBOX_SIZE, LETTERBOX_SIZE),
+        ),
+        (
+            Vec3::new(0.0, -outer_half_height - offset, 0.0),
+            Vec2::new(2.0 * LETTERBOX_SIZE, LETTERBOX_SIZE),
+        ),
+    ]
+    .iter()
+    {
+        commands
+            .spawn(SpriteComponents {
+                material: arena_materials.letterbox.clone(),
+                transform: Transform::from_translation(translation),
+                sprite: Sprite::new(size),
+                ..Default::default()
+            })
+            .with(Letterbox);
+    }
+}
+
+/// A wall the ball bounces off, losing a bit of speed
+pub fn spawn_wall(
+    commands: &mut Commands,
+    material: Handle<ColorMaterial>,
+    translation: Vec3,
+    size: Vec2,
+    name: &str,
+) {
+    commands
+        .spawn(SpriteComponents {
+            material,
+            transform: Transform::from_translation(translation),
+            sprite: Sprite::new(size),
+            ..Default::default()
+        })
+        .with(Collider)
+        .with(Bounce::Reflect)
+        .with(SpeedChange::SlowDown)
+        .with(Wall)
+        .with(Name(name.into()));
+}
+
+/// The wall behind the paddle, which takes the ball out of play
+pub fn spawn_bottom_wall(
+    commands: &mut Commands,
+    material: Handle<ColorMaterial>,
+    translation: Vec3,
+    size: Vec2,
+) {
+    commands
+        .spawn(SpriteComponents {
+            material,
+            transform: Transform::from_translation(translation),
+            sprite: Sprite::new(size),
+            ..Default::default()
+        })
+        .with(Collider)
+        .with(Bounce::Reflect)
+        .with(KillsBall)
+        .with(Wall)
+        .with(Name("Bottom wall".into()));
+}
@@ -0,0 +1,477 @@
+//! The ball: how it moves, spins, bounces off things and leaves a trail.
+
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::{FRAC_PI_4, PI},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bricks::Brick,
+    collision::{
+        collide, BallCollision, Bounce, Collider, ColorTransfer, Destructible, KillsBall, Points,
+        ResetsCombo, SpeedChange,
+    },
+    game::{color_to_vec4, DespawnOnEnd, FadeOut, Lives, Name, Options, PLAYING},
+    materials::{MaterialPool, Pooled},
+    schedule::{COLLISION, EFFECTS, MOVEMENT},
+    scoring::{BrickScored, Combo, Scoreboard},
+    state::{run_in, GameState, StateMachine},
+    tuning::Tuning,
+};
+
+pub struct BallPlugin;
+
+impl Plugin for BallPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<BallCollision>()
+            .add_system_to_stage(COLLISION, run_in(PLAYING, ball_collision_system.system()))
+            .add_system_to_stage(MOVEMENT, run_in(PLAYING, ball_movement_system.system()))
+            .add_system_to_stage(MOVEMENT, run_in(PLAYING, ball_rotation_system.system()))
+            .add_system_to_stage(EFFECTS, run_in(PLAYING, hit_system.system()))
+            .add_system_to_stage(EFFECTS, run_in(PLAYING, ball_lost_system.system()))
+            .add_system_to_stage(EFFECTS, run_in(PLAYING, change_color_system.system()))
+            .add_system_to_stage(EFFECTS, run_in(PLAYING, ball_trail_system.system()));
+    }
+}
+
+#[derive(Debug)]
+pub struct Ball {
+    pub velocity: Vec3,
+    pub rotation: f32,
+    pub rotational_velocity: f32,
+    pub spin: Spin,
+    pub last_paddle_offset: f32,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Spin {
+    Clockwise,
+    CounterCw,
+}
+
+/// A new ball heading down towards the paddle from the middle of the arena
+pub fn serve_ball(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    tuning: &Tuning,
+    color: Color,
+) {
+    spawn_ball(
+        commands,
+        materials,
+        Vec3::new(0.0, -30.0, 10.0),
+        Ball {
+            velocity: tuning.ball_speed * Vec3::new(1.0, -1.0, 0.0).normalize(),
+            rotation: FRAC_PI_4,
+            rotational_velocity: 2.0 * PI, // radians per second
+            spin: Spin::Clockwise,
+            last_paddle_offset: 0.0,
+        },
+        color,
+    );
+}
+
+pub fn spawn_ball(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    translation: Vec3,
+    ball: Ball,
+    color: Color,
+) {
+    commands
+        .spawn(SpriteComponents {
+            material: materials.add(color.into()),
+            transform: Transform {
+                translation,
+                rotation: Quat::from_rotation_z(ball.rotation),
+                ..Default::default()
+            },
+            sprite: Sprite::new(Vec2::new(30.0, 30.0)),
+            draw: Draw {
+                is_transparent: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(ball)
+        .with(DespawnOnEnd)
+        .with(Name("Ball".into()));
+}
+
+fn wrap(num: f32, min: f32, max: f32) -> f32 {
+    if num < min {
+        max - (min - num)
+    } else if num > max {
+        min - (max - num)
+    } else {
+        num
+    }
+}
+
+/// Finds what each ball will hit this frame, and sends a [`BallCollision`] for it
+fn ball_collision_system(
+    time: Res<Time>,
+    mut collision_events: ResMut<Events<BallCollision>>,
+    mut ball_query: Query<(Entity, &Ball, &Transform, &Sprite)>,
+    mut collider_query: Query<(Entity, &Collider, &Transform, &Sprite)>,
+) {
+    for (ball_entity, ball, ball_transform, ball_sprite) in &mut ball_query.iter() {
+        // check collision with walls, bricks and paddles
+        for (collider_entity, _collider, collider_transform, sprite) in &mut collider_query.iter() {
+            if let Some(collision) = collide(
+                ball_transform.translation,
+                ball_sprite.size,
+                collider_transform.translation,
+                sprite.size,
+                &ball.velocity,
+                time.delta_seconds,
+            ) {
+                collision_events.send(BallCollision {
+                    ball: ball_entity,
+                    other: collider_entity,
+                    normal: collision.normal(),
+                    time_of_impact: collision.time_of_impact(
+                        ball_transform.translation,
+                        &ball.velocity,
+                        time.delta_seconds,
+                    ),
+                });
+                // TODO: I think this is a tempfix for the ball escaping the arena, i.e. it can only hit collide with one entity only
+                // nope, ball still escapes - the correct fix is to allow for multiple collisions in one frame
+                // (e.g. the paddle AND the side wall, a brick AND a wall, top AND side walls)
+                break;
+            }
+        }
+    }
+}
+
+/// Breaks what is destructible, scores what is worth points and resets the combo
+fn hit_system(
+    mut commands: Commands,
+    mut collision_reader: Local<EventReader<BallCollision>>,
+    collision_events: Res<Events<BallCollision>>,
+    tuning: Res<Tuning>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut combo: ResMut<Combo>,
+    mut brick_scored_events: ResMut<Events<BrickScored>>,
+    collider_query: Query<(
+        &Collider,
+        &Transform,
+        Option<&Destructible>,
+        Option<&Points>,
+        Option<&ResetsCombo>,
+    )>,
+    brick_query: Query<&mut Brick>,
+) {
+    // a collider isn't removed until the end of the frame, so two balls can hit the same
+    // destructible collider in one frame, but it only breaks and scores once
+    let mut broken = HashSet::new();
+    for collision in collision_reader.iter(&collision_events) {
+        let other = collision.other;
+        if collider_query.get::<ResetsCombo>(other).is_ok() {
+            combo.hits = 0;
+        }
+        if collider_query.get::<Destructible>(other).is_ok() {
+            if !broken.insert(other) {
+                continue;
+            }
+            commands.insert_one(
+                other,
+                FadeOut {
+                    fade_out_time: tuning.fade_out_time,
+                    starting_color: Color::WHITE,
+                },
+            );
+            commands.remove_one::<Collider>(other);
+            if let Ok(mut brick) = brick_query.get_mut::<Brick>(other) {
+                brick.0 = false;
+            }
+        }
+        if let Ok(points) = collider_query.get::<Points>(other) {
+            combo.hits += 1;
+            let points = points.0 * combo.multiplier(tuning.max_combo_multiplier);
+            scoreboard.score += points;
+            brick_scored_events.send(BrickScored {
+                position: collider_query.get::<Transform>(other).unwrap().translation,
+                points,
+            });
+        }
+    }
+}
+
+/// Fades out the balls that hit something that kills them. Once they're all gone, a new ball is
+/// served after a countdown if there are lives left, and otherwise the game is lost.
+fn ball_lost_system(
+    mut commands: Commands,
+    mut collision_reader: Local<EventReader<BallCollision>>,
+    collision_events: Res<Events<BallCollision>>,
+    tuning: Res<Tuning>,
+    mut state: ResMut<StateMachine>,
+    mut combo: ResMut<Combo>,
+    mut lives: ResMut<Lives>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    kills_ball_query: Query<&KillsBall>,
+    mut ball_query: Query<(&Ball, &Handle<ColorMaterial>)>,
+) {
+    let mut ball_count = 0;
+    for (..) in &mut ball_query.iter() {
+        ball_count += 1;
+    }
+    let mut lost = false;
+    for collision in collision_reader.iter(&collision_events) {
+        if kills_ball_query.get::<KillsBall>(collision.other).is_ok() {
+            let material = ball_query
+                .get::<Handle<ColorMaterial>>(collision.ball)
+                .unwrap();
+            let color = materials.get(&*material).unwrap().color;
+            commands.insert_one(
+                collision.ball,
+                FadeOut {
+                    fade_out_time: tuning.fade_out_time,
+                    starting_color: color,
+                },
+            );
+            commands.remove_one::<Ball>(collision.ball);
+            combo.hits = 0;
+            ball_count -= 1;
+            lost = true;
+        }
+    }
+    if lost && ball_count <= 0 {
+        if lives.remaining > 0 {
+            lives.remaining -= 1;
+            serve_ball(&mut commands, &mut materials, &tuning, Color::WHITE);
+            state.set(GameState::Countdown);
+        } else {
+            state.set(GameState::Lose);
+        }
+    }
+}
+
+/// Passes colours between the balls and what they hit, see [`ColorTransfer`]
+fn change_color_system(
+    mut collision_reader: Local<EventReader<BallCollision>>,
+    collision_events: Res<Events<BallCollision>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    collider_query: Query<(&ColorTransfer, &Handle<ColorMaterial>)>,
+    ball_query: Query<&Handle<ColorMaterial>>,
+) {
+    for collision in collision_reader.iter(&collision_events) {
+        let transfer = match collider_query.get::<ColorTransfer>(collision.other) {
+            Ok(transfer) => *transfer,
+            Err(_) => continue,
+        };
+        let ball_material_handle = ball_query
+            .get::<Handle<ColorMaterial>>(collision.ball)
+            .unwrap();
+        let collider_material_handle = collider_query
+            .get::<Handle<ColorMaterial>>(collision.other)
+            .unwrap();
+        let old_color = color_to_vec4(materials.get(&*ball_material_handle).unwrap().color);
+        match transfer {
+            ColorTransfer::ToBall => {
+                let new_color =
+                    color_to_vec4(materials.get(&*collider_material_handle).unwrap().color);
+                let ball_material = materials.get_mut(&*ball_material_handle).unwrap();
+                ball_material.color = old_color.lerp(new_color, 0.5).into();
+            }
+            ColorTransfer::FromBall => {
+                if collision.normal.y() > 0.0 {
+                    let collider_material = materials.get_mut(&*collider_material_handle).unwrap();
+                    collider_material.color = old_color.into();
+                }
+            }
+        }
+    }
+}
+
+/// Moves the balls, bouncing the ones that hit something this frame
+fn ball_movement_system(
+    time: Res<Time>,
+    tuning: Res<Tuning>,
+    mut collision_reader: Local<EventReader<BallCollision>>,
+    collision_events: Res<Events<BallCollision>>,
+    collider_query: Query<(
+        &Collider,
+        &Transform,
+        &Sprite,
+        Option<&Bounce>,
+        Option<&SpeedChange>,
+    )>,
+    mut ball_query: Query<(Entity, &mut Ball, &mut Transform, &Sprite)>,
+) {
+    // clamp the timestep to stop the ball from escaping when the game starts
+    let delta_seconds = f32::min(0.2, time.delta_seconds);
+
+    // look up what was hit before borrowing the balls' transforms
+    let collisions: HashMap<
+        Entity,
+        (
+            BallCollision,
+            Option<Bounce>,
+            Option<SpeedChange>,
+            Vec3,
+            Vec2,
+        ),
+    > = collision_reader
+        .iter(&collision_events)
+        .map(|collision| {
+            let other = collision.other;
+            let bounce = collider_query
+                .get::<Bounce>(other)
+                .ok()
+                .map(|bounce| *bounce);
+            let speed_change = collider_query
+                .get::<SpeedChange>(other)
+                .ok()
+                .map(|speed_change| *speed_change);
+            let translation = collider_query.get::<Transform>(other).unwrap().translation;
+            let size = collider_query.get::<Sprite>(other).unwrap().size;
+            (
+                collision.ball,
+                (*collision, bounce, speed_change, translation, size),
+            )
+        })
+        .collect();
+
+    for (entity, mut ball, mut transform, sprite) in &mut ball_query.iter() {
+        // either we continue in the current direction with current velocity
+        // or we take two moves with flips, so we need a midpoint, and a new direction
+        let handle_collision = match collisions.get(&entity) {
+            None => None,
+            Some((collision, bounce, speed_change, collider_translation, collider_size)) => {
+                let normal = collision.normal;
+                let mut new_velocity = match bounce {
+                    Some(Bounce::Steer) if normal.y() > 0.0 && ball.velocity.y() < 0.0 => {
+                        let offset = transform.translation.x() - collider_translation.x();
+                        ball.spin = if offset < 0.0 {
+                            Spin::CounterCw
+                        } else {
+                            Spin::Clockwise
+                        };
+                        ball.last_paddle_offset = offset;
+                        let magnitude = ball.velocity.length();
+                        // the ball just touching the paddle's edge
+                        let max_offset = (collider_size.x() + sprite.size.x()) / 2.0;
+                        let angle = ball.last_paddle_offset.max(-max_offset).min(max_offset)
+                            / max_offset
+                            * (PI / 180.0 * tuning.max_paddle_angle);
+                        let x = angle.sin();
+                        let y = angle.cos();
+                        Vec3::new(x, y, 0.0) * magnitude
+                    }
+                    Some(Bounce::Reflect) => {
+                        let mut new_velocity = ball.velocity.clone();
+                        // reflect the ball when it collides
+                        // only reflect if the ball's velocity is going in the opposite direction of the collision
+                        // reflect velocity on the x-axis if we hit something on the x-axis
+                        if normal.x() * ball.velocity.x() < 0.0 {
+                            *new_velocity.x_mut() *= -1.0;
+                        }
+                        // reflect velocity on the y-axis if we hit something on the y-axis
+                        if normal.y() * ball.velocity.y() < 0.0 {
+                            *new_velocity.y_mut() *= -1.0;
+                        }
+                        new_velocity
+                    }
+                    Some(Bounce::Steer) | None => ball.velocity,
+                };
+                if let Some(speed_change) = speed_change {
+                    let magnitude = (new_velocity.length() + speed_change.amount(&tuning))
+                        .max(tuning.min_ball_speed);
+                    new_velocity *= magnitude / new_velocity.length();
+                }
+                // a hit on one side bounces from the start of the frame, and only a corner hit
+                // moves up to where the ball touches first
+                let midpoint = if normal.x() != 0.0 && normal.y() != 0.0 {
+                    collision.time_of_impact
+                } else {
+                    0.0
+                };
+                Some((midpoint, new_velocity))
+            }
+        };
+        if let Some((midpoint, new_velocity)) = handle_collision {
+            // half move
+            transform.translation += ball.velocity * delta_seconds * midpoint;
+            // update velocity
+            ball.velocity = new_velocity;
+            // a turn a second at the starting speed
+            ball.rotational_velocity = new_velocity.length() / tuning.ball_speed * 2.0 * PI;
+            // finish the move
+            transform.translation += ball.velocity * delta_seconds * (1.0 - midpoint);
+        } else {
+            transform.translation += ball.velocity * delta_seconds;
+        }
+    }
+}
+
+fn ball_rotation_system(time: Res<Time>, mut query: Query<(&Ball, &mut Transform)>) {
+    for (ball, mut transform) in &mut query.iter() {
+        // match ball.spin {
+        //     Spin::Clockwise => {
+        //         ball.rotation -= ball.rotational_velocity * time.delta_seconds;
+        //     }
+        //     Spin::CounterCw => {
+        //         ball.rotation += ball.rotational_velocity * time.delta_seconds;
+        //     }
+        // }
+        // ball.rotation = wrap(ball.rotation, 0.0, PI);
+        // *rotation = Rotation::from_rotation_z(ball.rotation);
+        // dbg!(&transform);
+        let current_angle = transform.rotation.to_axis_angle().1;
+        let new_angle = wrap(
+            current_angle
+                + ball.rotational_velocity
+                    * time.delta_seconds
+                    * match ball.spin {
+                        Spin::Clockwise => -1.0,
+                        Spin::CounterCw => 1.0,
+                    },
+            0.0,
+            PI,
+        );
+        transform.rotation = Quat::from_rotation_z(new_angle);
+    }
+}
+
+fn ball_trail_system(
+    mut commands: Commands,
+    options: Res<Options>,
+    tuning: Res<Tuning>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut pool: ResMut<MaterialPool>,
+    mut query: Query<(&Ball, &Transform, &Handle<ColorMaterial>)>,
+) {
+    if !options.ball_trail {
+        return;
+    }
+    for (_ball, &transform, material_handle) in &mut query.iter() {
+        let mut transform = transform;
+        transform.translation.set_z(0.0);
+        let color = materials.get(material_handle).unwrap().color;
+        let color = color_to_vec4(color).lerp(color_to_vec4(Color::WHITE), 0.4);
+        let color: Color = color.into();
+        let material = pool.take(&mut materials, color);
+        commands
+            .spawn(SpriteComponents {
+                material,
+                transform,
+                sprite: Sprite::new(Vec2::new(30.0, 30.0)),
+                draw: Draw {
+                    is_transparent: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with(DespawnOnEnd)
+            .with(Pooled)
+            .with(FadeOut {
+                fade_out_time: tuning.trail_fade_time,
+                starting_color: color,
+            });
+    }
+}
@@ -0,0 +1,253 @@
+//! The bricks, and the levels they're laid out in.
+
+use std::{fs, io, path::Path};
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::Arena,
+    collision::{Bounce, Collider, ColorTransfer, Destructible, Points, SpeedChange},
+    game::{DespawnOnEnd, GameRng, Name, PLAYING},
+    materials::MaterialPool,
+    state::{run_in, GameState, StateMachine},
+};
+
+pub struct BricksPlugin;
+
+impl Plugin for BricksPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Levels>()
+            .add_resource(CurrentLevel(0))
+            .add_system(run_in(PLAYING, check_win_condition_system.system()));
+    }
+}
+
+/// A brick, which has to be broken to win the level, and whether it is still standing
+pub struct Brick(pub bool);
+
+/// What kind of brick this is, which decides how many points it is worth
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum BrickType {
+    Plain,
+    Silver,
+    Gold,
+}
+
+impl BrickType {
+    /// The top row is gold, the one below it silver, and the rest plain
+    fn for_row(row: usize, rows: usize) -> Self {
+        if row + 1 == rows {
+            BrickType::Gold
+        } else if row + 2 == rows {
+            BrickType::Silver
+        } else {
+            BrickType::Plain
+        }
+    }
+
+    pub fn points(self) -> usize {
+        match self {
+            BrickType::Plain => 1,
+            BrickType::Silver => 2,
+            BrickType::Gold => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub name: String,
+    pub brick_rows: usize,
+    pub brick_columns: usize,
+    /// The height of the bottom row of bricks, from the middle of the arena
+    pub bricks_bottom: f32,
+    /// The arena the level is played in, the default one if the level file leaves it out
+    #[serde(default)]
+    pub arena: Arena,
+}
+
+/// The size of a brick, which is narrowed when a level's columns don't fit across its arena
+const BRICK_SIZE: (f32, f32) = (150.0, 30.0);
+
+/// The gap between bricks, and between the bricks and the side walls
+const BRICK_SPACING: f32 = 20.0;
+
+impl Level {
+    /// How wide the bricks are in `arena`, narrower than [`BRICK_SIZE`] if that many columns
+    /// wouldn't fit between the side walls
+    fn brick_width(&self, arena: &Arena) -> f32 {
+        let columns = self.brick_columns.max(1) as f32;
+        let room = 2.0 * arena.inner_half_width() - (columns + 1.0) * BRICK_SPACING;
+        (room / columns).min(BRICK_SIZE.0)
+    }
+
+    /// The height of the top of the top row of bricks
+    fn bricks_top(&self) -> f32 {
+        let rows = self.brick_rows.max(1) as f32;
+        self.bricks_bottom + (rows - 1.0) * (BRICK_SIZE.1 + BRICK_SPACING) + BRICK_SIZE.1 / 2.0
+    }
+
+    /// Whether the bricks fit inside the level's arena
+    fn fits(&self) -> bool {
+        self.brick_width(&self.arena) > 0.0 && self.bricks_top() < self.arena.inner_half_height()
+    }
+}
+
+/// The levels that can be played, in order: the built-in ones, unless a level file was given
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Levels(pub Vec<Level>);
+
+impl Default for Levels {
+    fn default() -> Self {
+        let level = |name: &str, brick_rows, bricks_bottom| Level {
+            name: name.into(),
+            brick_rows,
+            brick_columns: 5,
+            bricks_bottom,
+            arena: Arena::default(),
+        };
+        Levels(vec![
+            level("Warm up", 3, 100.0),
+            level("Classic", 4, 100.0),
+            level("Wall", 5, 50.0),
+        ])
+    }
+}
+
+impl Levels {
+    /// Read a list of levels, written as RON, from `path`
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let levels: Vec<Level> = ron::de::from_str(&contents)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if levels.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "there are no levels in the file",
+            ));
+        }
+        if let Some(level) = levels.iter().find(|level| !level.fits()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the bricks of level \"{}\" don't fit in its arena",
+                    level.name
+                ),
+            ));
+        }
+        Ok(Levels(levels))
+    }
+
+    /// Find a level by its number, counting from 1, or by its name
+    pub fn find(&self, number_or_name: &str) -> Option<usize> {
+        match number_or_name.parse::<usize>() {
+            Ok(number) if number >= 1 && number <= self.0.len() => Some(number - 1),
+            Ok(_) => None,
+            Err(_) => self
+                .0
+                .iter()
+                .position(|level| level.name.eq_ignore_ascii_case(number_or_name)),
+        }
+    }
+}
+
+/// Index into [`Levels`] of the level being played
+pub struct CurrentLevel(pub usize);
+
+pub fn spawn_brick(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    pool: &mut MaterialPool,
+    position: Vec3,
+    size: Vec2,
+    color: Color,
+    brick_type: BrickType,
+    name: String,
+) {
+    commands
+        .spawn(SpriteComponents {
+            material: pool.shared(materials, color),
+            sprite: Sprite::new(size),
+            transform: Transform::from_translation(position),
+            draw: Draw {
+                is_transparent: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(Collider)
+        .with(Bounce::Reflect)
+        .with(SpeedChange::SpeedUp)
+        .with(Destructible)
+        .with(Points(brick_type.points()))
+        .with(ColorTransfer::ToBall)
+        .with(Brick(true))
+        .with(brick_type)
+        .with(DespawnOnEnd)
+        .with(Name(name));
+}
+
+/// Lays out the bricks of `level` across `arena`
+pub fn spawn_bricks(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    pool: &mut MaterialPool,
+    rng: &mut GameRng,
+    level: &Level,
+    arena: &Arena,
+) {
+    let brick_rows = level.brick_rows;
+    let brick_columns = level.brick_columns;
+    let brick_spacing = BRICK_SPACING;
+    let brick_size = Vec2::new(level.brick_width(arena), BRICK_SIZE.1);
+    let bricks_width = brick_columns as f32 * (brick_size.x() + brick_spacing) - brick_spacing;
+    // center the bricks and move them up a bit
+    let bricks_offset = Vec3::new(
+        -(bricks_width - brick_size.x()) / 2.0,
+        level.bricks_bottom,
+        0.0,
+    );
+
+    for row in 0..brick_rows {
+        let y_position = row as f32 * (brick_size.y() + brick_spacing);
+        for column in 0..brick_columns {
+            let brick_position = Vec3::new(
+                column as f32 * (brick_size.x() + brick_spacing),
+                y_position,
+                0.0,
+            ) + bricks_offset;
+
+            let brick_type = BrickType::for_row(row, brick_rows);
+            let [r, g, b] = rng.rng.gen::<[u8; 3]>();
+            let color = match brick_type {
+                BrickType::Plain => Color::rgb_u8(r, g, b),
+                BrickType::Silver => Color::rgb(0.75, 0.75, 0.78),
+                BrickType::Gold => Color::rgb(0.85, 0.65, 0.13),
+            };
+            spawn_brick(
+                commands,
+                materials,
+                pool,
+                brick_position,
+                brick_size,
+                color,
+                brick_type,
+                format!("Brick {}-{}", row, column),
+            );
+        }
+    }
+}
+
+fn check_win_condition_system(mut state: ResMut<StateMachine>, mut brick_query: Query<&Brick>) {
+    let mut brick_count = 0;
+    for brick in &mut brick_query.iter() {
+        if brick.0 {
+            brick_count += 1;
+        }
+    }
+    if brick_count == 0 {
+        state.set(GameState::Win);
+    }
+}
@@ -0,0 +1,112 @@
+//! The command line options.
+
+use std::path::PathBuf;
+
+use crate::{difficulty::Difficulty, replay::DEFAULT_REPLAY_PATH};
+
+pub const USAGE: &str = "\
+An implementation of the classic game \"Breakout\"
+
+USAGE:
+    my_bevy_game [OPTIONS]
+
+OPTIONS:
+    --window-size <WIDTH>x<HEIGHT>    The size of the window, e.g. 1280x720
+    --fullscreen                      Fill the screen rather than opening a window
+    --level <NUMBER|NAME>             The level to start on, counting from 1
+    --level-file <FILE>               Play the levels in a RON file instead of the built-in ones
+    --seed <NUMBER>                   Seed the brick colours, to repeat an earlier run
+    --difficulty <DIFFICULTY>         Easy, Normal (the default), Hard, or Custom to play with
+                                      the tuning file as it is
+    --debug                           Show the debug overlay, also toggled with F3
+    --debug-draw                      Draw what the collision detection sees, also toggled
+                                      with F4
+    --headless                        Run without a window, e.g. to check a replay on a server
+    --replay <FILE>                   Play back a recorded run, quitting at the end if headless
+    --record <FILE>                   Record the run here, rather than to last_run.replay
+    --help                            Print this message";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    pub window_size: Option<(u32, u32)>,
+    pub fullscreen: bool,
+    /// The level's number or name, looked up once the levels are loaded
+    pub level: Option<String>,
+    pub level_file: Option<PathBuf>,
+    pub seed: Option<u64>,
+    pub difficulty: Difficulty,
+    pub debug: bool,
+    pub debug_draw: bool,
+    pub headless: bool,
+    pub replay: Option<PathBuf>,
+    pub record: PathBuf,
+    pub help: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            window_size: None,
+            fullscreen: false,
+            level: None,
+            level_file: None,
+            seed: None,
+            difficulty: Difficulty::Normal,
+            debug: false,
+            debug_draw: false,
+            headless: false,
+            replay: None,
+            record: DEFAULT_REPLAY_PATH.into(),
+            help: false,
+        }
+    }
+}
+
+impl Args {
+    /// Read the options from `args`, the first of which is the program's name
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--window-size" => parsed.window_size = Some(parse_size(&value()?)?),
+                "--fullscreen" => parsed.fullscreen = true,
+                "--level" => parsed.level = Some(value()?),
+                "--level-file" => parsed.level_file = Some(value()?.into()),
+                "--seed" => {
+                    let seed = value()?;
+                    parsed.seed = Some(
+                        seed.parse()
+                            .map_err(|_| format!("{} is not a valid seed", seed))?,
+                    );
+                }
+                "--difficulty" => {
+                    let difficulty = value()?;
+                    parsed.difficulty = Difficulty::from_name(&difficulty)
+                        .ok_or_else(|| format!("{} is not a difficulty", difficulty))?;
+                }
+                "--debug" => parsed.debug = true,
+                "--debug-draw" => parsed.debug_draw = true,
+                "--headless" => parsed.headless = true,
+                "--replay" => parsed.replay = Some(value()?.into()),
+                "--record" => parsed.record = value()?.into(),
+                "--help" | "-h" => parsed.help = true,
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Read a window size written as `<width>x<height>`
+fn parse_size(size: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("{} is not a window size, e.g. 1280x720", size);
+    let mut parts = size.splitn(2, 'x');
+    let width = parts.next().and_then(|width| width.parse().ok());
+    let height = parts.next().and_then(|height| height.parse().ok());
+    match (width, height) {
+        (Some(width), Some(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(invalid()),
+    }
+}
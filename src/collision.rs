@@ -0,0 +1,200 @@
+//! Collision detection between the ball and the rectangles it can hit, and the components that
+//! decide what happens when it hits them.
+//!
+//! Anything with a [`Collider`] can be hit. What the hit does is made up of the other components
+//! here, so the paddle is a collider that steers the ball, resets the combo and takes the ball's
+//! colour, while a brick reflects it, speeds it up, breaks, scores and gives the ball its colour.
+
+use bevy::prelude::*;
+
+use crate::tuning::Tuning;
+
+/// Determine whether two rectangles overlap during a frame.
+///
+/// The problem with Bevy's is that during a frame, one rectangle might be *very close*
+/// to another rectangle, then the following frame, it has moved >50% of its "width"
+/// into the rectangle, so this determines that the collision had approached from the
+/// opposite direction. You can also have multiple collisions during a frame (not yet implemented), and multiple
+/// frame collisions (not intentional). Additionally, it is possible to have both vertical and horizontal
+/// collisions at the same time i.e. outside corner to outside corner, or outside corner to inside corner.
+pub fn collide(
+    ball_pos: Vec3,
+    ball_size: Vec2,
+    other_pos: Vec3,
+    other_size: Vec2,
+    ball_velocity: &Vec3,
+    time_delta: f32,
+) -> Option<WillCollide> {
+    let a_min_prev = ball_pos.truncate() - ball_size / 2.0;
+    let a_max_prev = ball_pos.truncate() + ball_size / 2.0;
+    let a_min = a_min_prev + ball_velocity.truncate() * time_delta;
+    let a_max = a_max_prev + ball_velocity.truncate() * time_delta;
+    let b_min = other_pos.truncate() - other_size / 2.0;
+    let b_max = other_pos.truncate() + other_size / 2.0;
+
+    // check to see if the two rectangles are intersecting
+    if a_min.x() < b_max.x()
+        && a_max.x() > b_min.x()
+        && a_min.y() < b_max.y()
+        && a_max.y() > b_min.y()
+    {
+        let (x_collision, x_collision_site) = if a_max_prev.x() < b_min.x() && a_max.x() > b_min.x()
+        {
+            (CollisionX::Left, b_min.x() - ball_size.x() / 2.0)
+        } else if a_min_prev.x() > b_max.x() && a_min.x() < b_max.x() {
+            (CollisionX::Right, b_max.x() + ball_size.x() / 2.0)
+        } else {
+            (CollisionX::None, 0.0)
+        };
+
+        let (y_collision, y_collision_site) = if a_max_prev.y() < b_min.y() && a_max.y() > b_min.y()
+        {
+            (CollisionY::Bottom, b_min.y() - ball_size.y() / 2.0)
+        } else if a_min_prev.y() > b_max.y() && a_min.y() < b_max.y() {
+            (CollisionY::Top, b_max.y() + ball_size.y() / 2.0)
+        } else {
+            (CollisionY::None, 0.0)
+        };
+
+        Some(WillCollide {
+            x: (x_collision, x_collision_site),
+            y: (y_collision, y_collision_site),
+        })
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CollisionX {
+    Left,
+    Right,
+    None,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CollisionY {
+    Top,
+    Bottom,
+    None,
+}
+
+/// Which sides of the other rectangle the ball hits, and where the ball's centre is when it does
+#[derive(Debug)]
+pub struct WillCollide {
+    pub x: (CollisionX, f32),
+    pub y: (CollisionY, f32),
+}
+
+impl WillCollide {
+    /// The normal of the side (or corner) that was hit, pointing back out towards the ball. It is
+    /// zero if the ball was already inside.
+    pub fn normal(&self) -> Vec2 {
+        let x = match self.x.0 {
+            CollisionX::Left => -1.0,
+            CollisionX::Right => 1.0,
+            CollisionX::None => 0.0,
+        };
+        let y = match self.y.0 {
+            CollisionY::Top => 1.0,
+            CollisionY::Bottom => -1.0,
+            CollisionY::None => 0.0,
+        };
+        Vec2::new(x, y)
+    }
+
+    /// How far through the frame, from 0 to 1, the ball first touches the other rectangle, given
+    /// the same position, velocity and time delta that were passed to [`collide`]
+    pub fn time_of_impact(&self, ball_pos: Vec3, ball_velocity: &Vec3, time_delta: f32) -> f32 {
+        let x = if self.x.0 != CollisionX::None {
+            Some((self.x.1 - ball_pos.x()) / (ball_velocity.x() * time_delta))
+        } else {
+            None
+        };
+        let y = if self.y.0 != CollisionY::None {
+            Some((self.y.1 - ball_pos.y()) / (ball_velocity.y() * time_delta))
+        } else {
+            None
+        };
+        match (x, y) {
+            (Some(x), Some(y)) => x.min(y),
+            (Some(t), None) | (None, Some(t)) => t,
+            // already overlapping, so it's touching from the start
+            (None, None) => 0.0,
+        }
+        .max(0.0)
+        .min(1.0)
+    }
+}
+
+/// Sent in the [`COLLISION`] stage when a ball will hit a [`Collider`] this frame. The ball bounces
+/// in the [`MOVEMENT`] stage, and systems reacting to the hit run in the [`EFFECTS`] stage.
+///
+/// [`COLLISION`]: crate::schedule::COLLISION
+/// [`MOVEMENT`]: crate::schedule::MOVEMENT
+/// [`EFFECTS`]: crate::schedule::EFFECTS
+#[derive(Debug, Copy, Clone)]
+pub struct BallCollision {
+    pub ball: Entity,
+    /// The entity with the [`Collider`] that was hit
+    pub other: Entity,
+    /// See [`WillCollide::normal`]
+    pub normal: Vec2,
+    /// See [`WillCollide::time_of_impact`]
+    pub time_of_impact: f32,
+}
+
+/// Something the ball can hit
+pub struct Collider;
+
+/// How the ball bounces off a [`Collider`]. It goes straight through a collider without one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Bounce {
+    /// Reflect off the side that was hit
+    Reflect,
+    /// Landing on top sends the ball back up at an angle that depends on how far from the middle
+    /// it landed, and spins it the way it came from. Hitting any other side doesn't bounce.
+    Steer,
+}
+
+/// Changes the ball's speed each hit, by one of the amounts in the [`Tuning`], though never below
+/// its minimum speed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpeedChange {
+    /// Speeds up by [`Tuning::brick_speed_up`]
+    SpeedUp,
+    /// Slows down by [`Tuning::wall_slow_down`]
+    SlowDown,
+}
+
+impl SpeedChange {
+    pub fn amount(self, tuning: &Tuning) -> f32 {
+        match self {
+            SpeedChange::SpeedUp => tuning.brick_speed_up,
+            SpeedChange::SlowDown => -tuning.wall_slow_down,
+        }
+    }
+}
+
+/// Broken by the first hit, after which it fades out and can't be hit again
+pub struct Destructible;
+
+/// Scores this many points, times the combo multiplier, and adds to the combo each hit. A
+/// [`Destructible`] collider only scores for the hit that breaks it.
+#[derive(Debug, Copy, Clone)]
+pub struct Points(pub usize);
+
+/// Takes the ball out of play
+pub struct KillsBall;
+
+/// Ends the combo each hit, so that the next brick scores without a multiplier
+pub struct ResetsCombo;
+
+/// Whose colour rubs off on whom when the ball hits
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorTransfer {
+    /// The ball is tinted halfway towards the collider's colour
+    ToBall,
+    /// The collider takes on the ball's colour, but only when the ball lands on top of it
+    FromBall,
+}
@@ -0,0 +1,361 @@
+//! The debug overlay, shown while "Debug info" is on in the options, or toggled with F3.
+//!
+//! It shows the frame rate with a graph of recent frame times, how long each stage took, the
+//! entity and material counts, the balls' velocities and the normals of the last few hits. It also
+//! outlines every collider in the arena, see [`crate::debug_draw`].
+//!
+//! Bevy doesn't time systems one by one, so the overlay times each stage instead, see
+//! [`crate::schedule`] for which systems run in which.
+
+use std::{collections::VecDeque, time::Instant};
+
+use bevy::prelude::*;
+
+use crate::{
+    ball::Ball,
+    collision::BallCollision,
+    game::{Name, Options},
+    materials::MaterialPool,
+    schedule::{CLEANUP, COLLISION, EFFECTS, INPUT, MOVEMENT, UI},
+};
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<DebugMaterials>()
+            .init_resource::<FrameTimes>()
+            .init_resource::<StageTimings>()
+            .init_resource::<RecentHits>()
+            .add_startup_system(setup_debug.system())
+            .add_system_to_stage(INPUT, toggle_debug_system.system())
+            .add_system_to_stage(UI, frame_time_system.system())
+            .add_system_to_stage(UI, recent_hits_system.system())
+            .add_system_to_stage(UI, debug_text_system.system())
+            .add_system_to_stage(UI, frame_time_graph_system.system());
+        add_stage_timers(app);
+    }
+}
+
+/// The key that shows and hides the overlay
+pub const DEBUG_KEY: KeyCode = KeyCode::F3;
+
+/// How many lines of text the overlay has room for
+const TEXT_LINES: usize = 24;
+
+/// How many frames the graph shows
+const GRAPH_FRAMES: usize = 120;
+
+/// The graph's height for each millisecond a frame took, in pixels, up to [`GRAPH_MAX_HEIGHT`]
+const GRAPH_PIXELS_PER_MS: f32 = 3.0;
+const GRAPH_MAX_HEIGHT: f32 = 150.0;
+
+/// Frames slower than this are shown in the graph's warning colour
+const SLOW_FRAME_MS: f32 = 1000.0 / 30.0;
+
+/// How many hits are listed
+const RECENT_HITS: usize = 4;
+
+/// How much of each new stage timing goes into its average, so that the numbers are readable
+const TIMING_SMOOTHING: f32 = 0.05;
+
+/// The stages timed, each with the name of the marker stage added after it
+const TIMED_STAGES: [(&str, &str); 7] = [
+    (INPUT, "debug_timing_input"),
+    (COLLISION, "debug_timing_collision"),
+    (MOVEMENT, "debug_timing_movement"),
+    (stage::UPDATE, "debug_timing_update"),
+    (EFFECTS, "debug_timing_effects"),
+    (CLEANUP, "debug_timing_cleanup"),
+    (UI, "debug_timing_ui"),
+];
+
+/// Before the first timed stage
+const TIMING_START: &str = "debug_timing_start";
+
+const TEXT_COLOR: Color = Color::rgb(0.2, 0.2, 0.8);
+
+struct DebugMaterials {
+    bar: Handle<ColorMaterial>,
+    slow_bar: Handle<ColorMaterial>,
+}
+
+impl FromResources for DebugMaterials {
+    fn from_resources(resources: &Resources) -> Self {
+        let mut materials = resources.get_mut::<Assets<ColorMaterial>>().unwrap();
+        DebugMaterials {
+            bar: materials.add(Color::rgb(0.2, 0.2, 0.8).into()),
+            slow_bar: materials.add(Color::rgb(0.9, 0.2, 0.2).into()),
+        }
+    }
+}
+
+/// How long the most recent frames took, in milliseconds, oldest first
+#[derive(Default)]
+pub struct FrameTimes(pub VecDeque<f32>);
+
+/// How long each timed stage took, in milliseconds, averaged over recent frames
+#[derive(Default)]
+pub struct StageTimings {
+    last_mark: Option<Instant>,
+    pub millis: Vec<(&'static str, f32)>,
+}
+
+impl StageTimings {
+    /// Called between stages, timing `stage` as the time since the last mark
+    fn mark(&mut self, stage: Option<&'static str>) {
+        let now = Instant::now();
+        if let (Some(stage), Some(last_mark)) = (stage, self.last_mark) {
+            let millis = (now - last_mark).as_secs_f32() * 1000.0;
+            match self.millis.iter_mut().find(|(name, _)| *name == stage) {
+                Some((_, average)) => *average += (millis - *average) * TIMING_SMOOTHING,
+                None => self.millis.push((stage, millis)),
+            }
+        }
+        self.last_mark = Some(now);
+    }
+}
+
+/// The last few things the balls hit, as shown in the overlay
+#[derive(Default)]
+struct RecentHits(VecDeque<String>);
+
+/// One line of the overlay's text
+struct DebugText(usize);
+
+/// One bar of the frame time graph, the oldest frame first
+struct GraphBar(usize);
+
+/// Adds a stage after each timed stage, with a system that marks the time it was reached
+fn add_stage_timers(app: &mut AppBuilder) {
+    app.add_stage_before(INPUT, TIMING_START)
+        .add_system_to_stage(TIMING_START, timing_mark(None).thread_local_system());
+    for &(stage, marker_stage) in TIMED_STAGES.iter() {
+        app.add_stage_after(stage, marker_stage)
+            .add_system_to_stage(marker_stage, timing_mark(Some(stage)).thread_local_system());
+    }
+}
+
+fn timing_mark(
+    stage: Option<&'static str>,
+) -> impl FnMut(&mut World, &mut Resources) + Send + Sync + 'static {
+    move |_world, resources| resources.get_mut::<StageTimings>().unwrap().mark(stage)
+}
+
+fn setup_debug(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    debug_materials: Res<DebugMaterials>,
+) {
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let none = materials.add(Color::NONE.into());
+    commands
+        // text, below the score
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(45.0),
+                    left: Val::Px(5.0),
+                    ..Default::default()
+                },
+                // UI is laid out bottom to top, so reverse it to list the lines top down
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexStart,
+                ..Default::default()
+            },
+            material: none.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for line in 0..TEXT_LINES {
+                parent
+                    .spawn(TextComponents {
+                        text: Text {
+                            font: font.clone(),
+                            value: "".to_string(),
+                            style: TextStyle {
+                                color: TEXT_COLOR,
+                                font_size: 20.0,
+                            },
+                        },
+                        ..Default::default()
+                    })
+                    .with(DebugText(line));
+            }
+        })
+        // frame time graph, in the bottom left corner
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(5.0),
+                    left: Val::Px(5.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::Row,
+                // which is the bottom, so the bars grow upwards
+                align_items: AlignItems::FlexStart,
+                ..Default::default()
+            },
+            material: none,
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for bar in 0..GRAPH_FRAMES {
+                parent
+                    .spawn(NodeComponents {
+                        style: Style {
+                            size: Size::new(Val::Px(2.0), Val::Px(0.0)),
+                            margin: Rect {
+                                right: Val::Px(1.0),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        material: debug_materials.bar.clone(),
+                        ..Default::default()
+                    })
+                    .with(GraphBar(bar));
+            }
+        });
+}
+
+fn toggle_debug_system(keyboard_input: Res<Input<KeyCode>>, mut options: ResMut<Options>) {
+    if keyboard_input.just_pressed(DEBUG_KEY) {
+        options.show_debug_info = !options.show_debug_info;
+    }
+}
+
+/// Keeps the frame times for the graph, even while it's hidden, so it's full when it's shown
+fn frame_time_system(time: Res<Time>, mut frame_times: ResMut<FrameTimes>) {
+    if frame_times.0.len() == GRAPH_FRAMES {
+        frame_times.0.pop_front();
+    }
+    frame_times.0.push_back(time.delta_seconds * 1000.0);
+}
+
+/// Notes down what the balls hit this frame, with the normals of the sides they hit
+fn recent_hits_system(
+    mut collision_reader: Local<EventReader<BallCollision>>,
+    collision_events: Res<Events<BallCollision>>,
+    mut recent_hits: ResMut<RecentHits>,
+    name_query: Query<&Name>,
+) {
+    for collision in collision_reader.iter(&collision_events) {
+        let name = match name_query.get::<Name>(collision.other) {
+            Ok(name) => name.0.clone(),
+            Err(_) => "?".to_string(),
+        };
+        if recent_hits.0.len() == RECENT_HITS {
+            recent_hits.0.pop_front();
+        }
+        recent_hits.0.push_back(format!(
+            "{}: normal ({:.0}, {:.0})",
+            name,
+            collision.normal.x(),
+            collision.normal.y()
+        ));
+    }
+}
+
+fn debug_text_system(
+    options: Res<Options>,
+    time: Res<Time>,
+    stage_timings: Res<StageTimings>,
+    materials: Res<Assets<ColorMaterial>>,
+    pool: Res<MaterialPool>,
+    recent_hits: Res<RecentHits>,
+    mut entity_query: Query<Entity>,
+    mut color_handle_query: Query<&Handle<ColorMaterial>>,
+    mut ball_query: Query<&Ball>,
+    mut text_query: Query<(&mut Text, &DebugText)>,
+) {
+    let mut lines = Vec::new();
+    if options.show_debug_info {
+        let mut entity_count = 0;
+        for _ in &mut entity_query.iter() {
+            entity_count += 1;
+        }
+        let mut color_handle_count = 0;
+        for _ in &mut color_handle_query.iter() {
+            color_handle_count += 1;
+        }
+
+        lines.push(format!(
+            "FPS: {:.0} ({:.1} ms)",
+            1.0 / time.delta_seconds,
+            time.delta_seconds * 1000.0
+        ));
+        lines.push(format!("Entities: {}", entity_count));
+        let (shared, taken, free) = pool.counts();
+        lines.push(format!(
+            "Color Materials: {} ({} shared, {} in use, {} free)",
+            materials.iter().count(),
+            shared,
+            taken,
+            free
+        ));
+        lines.push(format!("Color Handles: {}", color_handle_count));
+
+        lines.push("Stage timings:".to_string());
+        for (stage, millis) in stage_timings.millis.iter() {
+            lines.push(format!("    {}: {:.2} ms", stage, millis));
+        }
+
+        lines.push("Balls:".to_string());
+        for ball in &mut ball_query.iter() {
+            lines.push(format!(
+                "    velocity ({:.0}, {:.0}), speed {:.0}",
+                ball.velocity.x(),
+                ball.velocity.y(),
+                ball.velocity.length()
+            ));
+        }
+
+        lines.push("Last hits:".to_string());
+        for hit in recent_hits.0.iter().rev() {
+            lines.push(format!("    {}", hit));
+        }
+    }
+    if lines.len() > TEXT_LINES {
+        lines.truncate(TEXT_LINES - 1);
+        lines.push("    ...".to_string());
+    }
+
+    for (mut text, line) in &mut text_query.iter() {
+        let text_value = lines.get(line.0).map(String::as_str).unwrap_or("");
+        if text.value != text_value {
+            text.value = text_value.to_string();
+        }
+    }
+}
+
+fn frame_time_graph_system(
+    options: Res<Options>,
+    frame_times: Res<FrameTimes>,
+    debug_materials: Res<DebugMaterials>,
+    mut bar_query: Query<(&GraphBar, &mut Style, &mut Handle<ColorMaterial>)>,
+) {
+    // the newest frame on the right
+    let first_bar = GRAPH_FRAMES - frame_times.0.len();
+    for (bar, mut style, mut material) in &mut bar_query.iter() {
+        let millis = match bar.0.checked_sub(first_bar) {
+            Some(frame) if options.show_debug_info => frame_times.0[frame],
+            _ => 0.0,
+        };
+        let height = (millis * GRAPH_PIXELS_PER_MS).min(GRAPH_MAX_HEIGHT);
+        if style.size.height != Val::Px(height) {
+            style.size.height = Val::Px(height);
+        }
+        let bar_material = if millis > SLOW_FRAME_MS {
+            &debug_materials.slow_bar
+        } else {
+            &debug_materials.bar
+        };
+        if *material != *bar_material {
+            *material = bar_material.clone();
+        }
+    }
+}
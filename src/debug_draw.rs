@@ -0,0 +1,194 @@
+//! Drawing what the collision detection sees, toggled with F4 or "Debug draw" in the options.
+//!
+//! Every collider and ball is outlined, as [`collide`] sees them. Each ball also gets an arrow for
+//! its velocity, the box [`collide`] tests it at the end of the frame, and the swept box it passes
+//! through on the way there. When a ball hits something, the point it touches and the normal of
+//! the side it hits flash for a moment, in red if the ball was already inside, with no normal.
+//!
+//! The outlines are also drawn while the debug overlay is on, see [`crate::debug`].
+//!
+//! [`collide`]: crate::collision::collide
+
+use bevy::prelude::*;
+
+use crate::{
+    ball::Ball,
+    collision::{BallCollision, Collider},
+    debug_lines::{DebugLines, DebugLinesPlugin},
+    game::Options,
+    schedule::{COLLISION, INPUT, UI},
+};
+
+/// Add after the [`BallPlugin`](crate::ball::BallPlugin), so that the hits are flashed the frame
+/// they're found
+pub struct DebugDrawPlugin;
+
+impl Plugin for DebugDrawPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(DebugLinesPlugin)
+            .init_resource::<ContactFlashes>()
+            .add_system_to_stage(INPUT, toggle_debug_draw_system.system())
+            // before the balls move, to see what the collision detection sees
+            .add_system_to_stage(COLLISION, ball_sweep_system.system())
+            .add_system_to_stage(COLLISION, contact_system.system())
+            .add_system_to_stage(UI, collider_outline_system.system())
+            .add_system_to_stage(UI, contact_flash_system.system());
+    }
+}
+
+/// The key that turns debug drawing on and off
+pub const DEBUG_DRAW_KEY: KeyCode = KeyCode::F4;
+
+/// How far ahead the velocity arrow reaches, in seconds
+const VELOCITY_ARROW_TIME: f32 = 0.1;
+
+/// How long a contact flashes for, in seconds
+const FLASH_TIME: f32 = 0.5;
+
+/// How long the normal drawn at a contact is, in world units
+const NORMAL_LENGTH: f32 = 40.0;
+
+/// The size of the cross marking a contact point, in world units
+const CONTACT_SIZE: f32 = 8.0;
+
+const OUTLINE_COLOR: Color = Color::rgb(0.0, 0.8, 0.0);
+const BALL_OUTLINE_COLOR: Color = Color::rgb(0.9, 0.1, 0.9);
+const VELOCITY_COLOR: Color = Color::rgb(1.0, 1.0, 0.0);
+const END_BOX_COLOR: Color = Color::rgb(1.0, 0.5, 0.0);
+const SWEPT_BOX_COLOR: Color = Color::rgb(0.5, 0.3, 0.0);
+const CONTACT_COLOR: Color = Color::rgb(0.0, 1.0, 1.0);
+const INSIDE_COLOR: Color = Color::rgb(1.0, 0.0, 0.0);
+
+/// A hit being flashed
+struct ContactFlash {
+    /// Where the ball touched what it hit
+    point: Vec2,
+    /// See [`BallCollision::normal`]
+    normal: Vec2,
+    remaining: f32,
+}
+
+#[derive(Default)]
+struct ContactFlashes(Vec<ContactFlash>);
+
+fn toggle_debug_draw_system(keyboard_input: Res<Input<KeyCode>>, mut options: ResMut<Options>) {
+    if keyboard_input.just_pressed(DEBUG_DRAW_KEY) {
+        options.debug_draw = !options.debug_draw;
+    }
+}
+
+/// Draws each ball's velocity, where it will be at the end of the frame and what it passes through
+/// on the way
+fn ball_sweep_system(
+    time: Res<Time>,
+    options: Res<Options>,
+    mut lines: ResMut<DebugLines>,
+    mut ball_query: Query<(&Ball, &Transform, &Sprite)>,
+) {
+    if !options.debug_draw {
+        return;
+    }
+    for (ball, transform, sprite) in &mut ball_query.iter() {
+        let start = transform.translation.truncate();
+        let velocity = ball.velocity.truncate();
+        let movement = velocity * time.delta_seconds;
+        lines.rect(start + movement, sprite.size, END_BOX_COLOR);
+        lines.rect(
+            start + movement / 2.0,
+            sprite.size + Vec2::new(movement.x().abs(), movement.y().abs()),
+            SWEPT_BOX_COLOR,
+        );
+        lines.arrow(
+            start,
+            start + velocity * VELOCITY_ARROW_TIME,
+            VELOCITY_COLOR,
+        );
+    }
+}
+
+/// Notes where each ball touches what it hits this frame, to flash it
+fn contact_system(
+    time: Res<Time>,
+    options: Res<Options>,
+    mut collision_reader: Local<EventReader<BallCollision>>,
+    collision_events: Res<Events<BallCollision>>,
+    mut flashes: ResMut<ContactFlashes>,
+    ball_query: Query<(&Ball, &Transform, &Sprite)>,
+) {
+    for collision in collision_reader.iter(&collision_events) {
+        if !options.debug_draw {
+            continue;
+        }
+        let ball = ball_query.get::<Ball>(collision.ball).unwrap();
+        let transform = ball_query.get::<Transform>(collision.ball).unwrap();
+        let sprite = ball_query.get::<Sprite>(collision.ball).unwrap();
+        let center = transform.translation.truncate()
+            + ball.velocity.truncate() * time.delta_seconds * collision.time_of_impact;
+        flashes.0.push(ContactFlash {
+            // the side of the ball facing what it hit
+            point: center - collision.normal * sprite.size / 2.0,
+            normal: collision.normal,
+            remaining: FLASH_TIME,
+        });
+    }
+}
+
+/// Outlines what the balls can hit, and the balls themselves, while either the debug overlay or
+/// debug drawing is on
+fn collider_outline_system(
+    options: Res<Options>,
+    mut lines: ResMut<DebugLines>,
+    mut collider_query: Query<(&Collider, &Transform, &Sprite)>,
+    mut ball_query: Query<(&Ball, &Transform, &Sprite)>,
+) {
+    if !options.show_debug_info && !options.debug_draw {
+        return;
+    }
+    for (_collider, transform, sprite) in &mut collider_query.iter() {
+        lines.rect(transform.translation.truncate(), sprite.size, OUTLINE_COLOR);
+    }
+    for (_ball, transform, sprite) in &mut ball_query.iter() {
+        lines.rect(
+            transform.translation.truncate(),
+            sprite.size,
+            BALL_OUTLINE_COLOR,
+        );
+    }
+}
+
+/// Draws the contact points and normals, fading them out
+fn contact_flash_system(
+    time: Res<Time>,
+    options: Res<Options>,
+    mut flashes: ResMut<ContactFlashes>,
+    mut lines: ResMut<DebugLines>,
+) {
+    if !options.debug_draw {
+        flashes.0.clear();
+        return;
+    }
+    for flash in flashes.0.iter_mut() {
+        flash.remaining -= time.delta_seconds;
+    }
+    flashes.0.retain(|flash| flash.remaining > 0.0);
+
+    for flash in flashes.0.iter() {
+        let inside = flash.normal == Vec2::zero();
+        let mut color = if inside { INSIDE_COLOR } else { CONTACT_COLOR };
+        // in steps, so that the fading lines share a few materials
+        color.a = (flash.remaining / FLASH_TIME * 10.0).ceil() / 10.0;
+        let (across, up) = (
+            Vec2::new(CONTACT_SIZE / 2.0, 0.0),
+            Vec2::new(0.0, CONTACT_SIZE / 2.0),
+        );
+        lines.line(flash.point - across, flash.point + across, color);
+        lines.line(flash.point - up, flash.point + up, color);
+        if !inside {
+            lines.arrow(
+                flash.point,
+                flash.point + flash.normal.normalize() * NORMAL_LENGTH,
+                color,
+            );
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Lines drawn over the arena for one frame, for debugging.
+//!
+//! Any system before the [`DEBUG_DRAW`] stage can draw with [`DebugLines`]. Each line is a thin
+//! sprite, and the sprites are kept from frame to frame and hidden when they're not needed, rather
+//! than spawned and despawned every frame.
+//!
+//! [`DEBUG_DRAW`]: crate::schedule::DEBUG_DRAW
+
+use bevy::prelude::*;
+
+use crate::{arena::ArenaView, materials::MaterialPool, schedule::DEBUG_DRAW};
+
+pub struct DebugLinesPlugin;
+
+impl Plugin for DebugLinesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<DebugLines>()
+            .add_system_to_stage(DEBUG_DRAW, debug_lines_system.system());
+    }
+}
+
+/// How wide the lines are, in window pixels
+const LINE_WIDTH: f32 = 2.0;
+
+/// In front of everything else in the arena
+const LINE_Z: f32 = 500.0;
+
+/// The lines to draw this frame, cleared once they're drawn
+#[derive(Default)]
+pub struct DebugLines {
+    lines: Vec<Line>,
+}
+
+struct Line {
+    start: Vec2,
+    end: Vec2,
+    color: Color,
+}
+
+impl DebugLines {
+    pub fn line(&mut self, start: Vec2, end: Vec2, color: Color) {
+        self.lines.push(Line { start, end, color });
+    }
+
+    /// The outline of a rectangle
+    pub fn rect(&mut self, center: Vec2, size: Vec2, color: Color) {
+        let half_x = Vec2::new(size.x() / 2.0, 0.0);
+        let half_y = Vec2::new(0.0, size.y() / 2.0);
+        let (top_left, top_right) = (center - half_x + half_y, center + half_x + half_y);
+        let (bottom_left, bottom_right) = (center - half_x - half_y, center + half_x - half_y);
+        self.line(top_left, top_right, color);
+        self.line(top_right, bottom_right, color);
+        self.line(bottom_right, bottom_left, color);
+        self.line(bottom_left, top_left, color);
+    }
+
+    /// A line from `start` to `end` with a head at `end`
+    pub fn arrow(&mut self, start: Vec2, end: Vec2, color: Color) {
+        self.line(start, end, color);
+        let direction = end - start;
+        if direction.length() == 0.0 {
+            return;
+        }
+        let back = direction.normalize() * -ARROW_HEAD_LENGTH;
+        self.line(end, end + rotate(back, ARROW_HEAD_ANGLE), color);
+        self.line(end, end + rotate(back, -ARROW_HEAD_ANGLE), color);
+    }
+}
+
+const ARROW_HEAD_LENGTH: f32 = 10.0;
+const ARROW_HEAD_ANGLE: f32 = 0.5;
+
+fn rotate(v: Vec2, angle: f32) -> Vec2 {
+    let (sin, cos) = angle.sin_cos();
+    Vec2::new(v.x() * cos - v.y() * sin, v.x() * sin + v.y() * cos)
+}
+
+/// Marks a sprite used to draw a line
+struct LineSprite;
+
+/// Moves a line sprite onto each line drawn this frame, spawning more if there aren't enough, and
+/// hides the rest
+fn debug_lines_system(
+    mut commands: Commands,
+    view: Res<ArenaView>,
+    mut lines: ResMut<DebugLines>,
+    mut pool: ResMut<MaterialPool>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut sprite_query: Query<(
+        &LineSprite,
+        &mut Transform,
+        &mut Sprite,
+        &mut Draw,
+        &mut Handle<ColorMaterial>,
+    )>,
+) {
+    // the same width in the window, however the camera is scaled
+    let width = LINE_WIDTH * view.scale;
+    let mut lines = lines.lines.drain(..);
+    for (_line_sprite, mut transform, mut sprite, mut draw, mut material) in
+        &mut sprite_query.iter()
+    {
+        match lines.next() {
+            Some(line) => {
+                let (line_transform, size) = place(&line, width);
+                *transform = line_transform;
+                sprite.size = size;
+                let line_material = pool.shared(&mut materials, line.color);
+                if *material != line_material {
+                    *material = line_material;
+                }
+                draw.is_visible = true;
+            }
+            None => draw.is_visible = false,
+        }
+    }
+    for line in lines {
+        let (transform, size) = place(&line, width);
+        commands
+            .spawn(SpriteComponents {
+                material: pool.shared(&mut materials, line.color),
+                transform,
+                sprite: Sprite::new(size),
+                draw: Draw {
+                    is_transparent: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with(LineSprite);
+    }
+}
+
+/// Where a line's sprite goes, and its size
+fn place(line: &Line, width: f32) -> (Transform, Vec2) {
+    let direction = line.end - line.start;
+    let middle = (line.start + line.end) / 2.0;
+    let transform = Transform {
+        translation: Vec3::new(middle.x(), middle.y(), LINE_Z),
+        rotation: Quat::from_rotation_z(direction.y().atan2(direction.x())),
+        ..Default::default()
+    };
+    // a little longer than the line, so that the corners of a rectangle meet
+    (transform, Vec2::new(direction.length() + width, width))
+}
@@ -0,0 +1,58 @@
+//! Despawning, which happens in one place at the end of the frame.
+//!
+//! Systems ask for an entity to be despawned through [`Despawns`] rather than with `Commands`, so
+//! that two systems despawning the same entity, or one despawning an entity that is already gone,
+//! can't crash the game. Pooled materials are given back to the [`MaterialPool`] on the way out.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::materials::{MaterialPool, Pooled};
+
+/// The entities to despawn at the end of this frame
+#[derive(Default)]
+pub struct Despawns {
+    /// Whether each entity's children are despawned with it
+    requested: HashMap<Entity, bool>,
+}
+
+impl Despawns {
+    pub fn despawn(&mut self, entity: Entity) {
+        self.requested.entry(entity).or_insert(false);
+    }
+
+    /// Despawn `entity` and all of its children
+    pub fn despawn_recursive(&mut self, entity: Entity) {
+        self.requested.insert(entity, true);
+    }
+}
+
+/// Despawns everything asked for this frame, in the [`DESPAWN`] stage
+///
+/// [`DESPAWN`]: crate::schedule::DESPAWN
+pub fn despawn_system(world: &mut World, resources: &mut Resources) {
+    let requested = std::mem::take(&mut resources.get_mut::<Despawns>().unwrap().requested);
+    if requested.is_empty() {
+        return;
+    }
+    let mut pool = resources.get_mut::<MaterialPool>().unwrap();
+    let mut commands = Commands::default();
+    for (entity, recursive) in requested {
+        if !world.contains(entity) {
+            continue;
+        }
+        if world.get::<Pooled>(entity).is_ok() {
+            if let Ok(handle) = world.get::<Handle<ColorMaterial>>(entity) {
+                pool.give_back(handle.clone());
+            }
+        }
+        if recursive {
+            commands.despawn_recursive(entity);
+        } else {
+            commands.despawn(entity);
+        }
+    }
+    drop(pool);
+    commands.apply(world, resources);
+}
@@ -0,0 +1,150 @@
+//! Difficulty presets, which set the speeds, the paddle and the lives over the tuning file.
+//!
+//! [`Difficulty::Custom`] plays with the tuning file as it is. The other presets replace its
+//! numbers for the ball, the paddle and the lives, and leave the rest alone. There are no
+//! power-ups yet, so there are no drop rates to set.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    save::SaveSlot,
+    state::{GameState, StateMachine},
+    tuning::{CustomTuning, Tuning},
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Custom,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty::Normal
+    }
+}
+
+/// The difficulty the game in progress started at, which it is played, saved and scored at. A
+/// change from the pause menu's options applies from the next game.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct GameDifficulty(pub Difficulty);
+
+/// The numbers a preset sets
+struct Preset {
+    ball_speed: f32,
+    brick_speed_up: f32,
+    wall_slow_down: f32,
+    min_ball_speed: f32,
+    paddle_width: f32,
+    paddle_speed: f32,
+    lives: usize,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Custom,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Custom => "Custom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|difficulty| difficulty.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The one after this, going round, for the options menu
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&d| d == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Normal is the default tuning, so that a game without a tuning file plays the same
+    fn preset(self) -> Option<Preset> {
+        match self {
+            Difficulty::Easy => Some(Preset {
+                ball_speed: 300.0,
+                brick_speed_up: 20.0,
+                wall_slow_down: 30.0,
+                min_ball_speed: 100.0,
+                paddle_width: 160.0,
+                paddle_speed: 550.0,
+                lives: 5,
+            }),
+            Difficulty::Normal => Some(Preset {
+                ball_speed: 400.0,
+                brick_speed_up: 30.0,
+                wall_slow_down: 20.0,
+                min_ball_speed: 100.0,
+                paddle_width: 120.0,
+                paddle_speed: 500.0,
+                lives: 3,
+            }),
+            Difficulty::Hard => Some(Preset {
+                ball_speed: 500.0,
+                brick_speed_up: 40.0,
+                wall_slow_down: 10.0,
+                min_ball_speed: 200.0,
+                paddle_width: 90.0,
+                paddle_speed: 500.0,
+                lives: 1,
+            }),
+            Difficulty::Custom => None,
+        }
+    }
+
+    /// `custom` with this difficulty's preset applied
+    pub fn apply(self, custom: &Tuning) -> Tuning {
+        let mut tuning = custom.clone();
+        if let Some(preset) = self.preset() {
+            tuning.ball_speed = preset.ball_speed;
+            tuning.brick_speed_up = preset.brick_speed_up;
+            tuning.wall_slow_down = preset.wall_slow_down;
+            tuning.min_ball_speed = preset.min_ball_speed;
+            tuning.paddle_width = preset.paddle_width;
+            tuning.paddle_speed = preset.paddle_speed;
+            tuning.lives = preset.lives;
+        }
+        tuning
+    }
+}
+
+/// Keeps the [`Tuning`] the game plays with up to date with the [`GameDifficulty`] and the tuning
+/// file, from the frame after either changes. The [`GameDifficulty`] follows the difficulty
+/// chosen until a game starts, in [`GameState::Restarting`], and then stays put until the next. A
+/// continued game is played at the difficulty it was saved at, leaving the one chosen alone.
+pub fn difficulty_system(
+    state: Res<StateMachine>,
+    difficulty: Res<Difficulty>,
+    save_slot: Res<SaveSlot>,
+    custom: Res<CustomTuning>,
+    mut applied: Local<Option<(Difficulty, Tuning)>>,
+    mut game_difficulty: ResMut<GameDifficulty>,
+    mut tuning: ResMut<Tuning>,
+) {
+    if matches!(state.current(), GameState::Title | GameState::Restarting) {
+        game_difficulty.0 = match &save_slot.pending {
+            Some(game) => game.difficulty(),
+            None => *difficulty,
+        };
+    }
+    let current = Some((game_difficulty.0, custom.0.clone()));
+    if *applied != current {
+        *tuning = game_difficulty.0.apply(&custom.0);
+        *applied = current;
+    }
+}
@@ -0,0 +1,285 @@
+//! The core of the game: the state machine, the spawning and clearing away of levels, and the
+//! components and resources shared by the other plugins.
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    arena::Arena,
+    ball::serve_ball,
+    bricks::{spawn_bricks, CurrentLevel, Levels},
+    despawn::{despawn_system, Despawns},
+    difficulty::{difficulty_system, Difficulty, GameDifficulty},
+    materials::{MaterialPool, Pooled},
+    paddle::spawn_paddle,
+    save::{load_game, SaveSlot},
+    schedule::{self, CLEANUP, DESPAWN},
+    scoring::{Combo, LevelTimer, Scoreboard},
+    state::{
+        on_enter, run_in, state_transition_system, GameState, StateMachine, STATE_HOOKS,
+        STATE_TRANSITION,
+    },
+    tuning::{CustomTuning, Tuning},
+};
+
+/// Sets up the state machine and spawns the level, which the other gameplay plugins build on. It
+/// has to be added before them, as it adds the stages they use.
+pub struct GamePlugin {
+    /// The seed of the [`GameRng`]
+    pub seed: u64,
+}
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(StateMachine::new(GameState::Title))
+            .add_resource(GameRng::new(self.seed))
+            // set from the tuning on entering the countdown
+            .add_resource(Countdown { remaining: 0.0 })
+            .add_resource(Options {
+                show_debug_info: true,
+                debug_draw: false,
+                ball_trail: true,
+            })
+            // nowhere to save to, unless the SavePlugin is added too
+            .init_resource::<SaveSlot>()
+            .init_resource::<MaterialPool>()
+            .add_resource(Lives { remaining: 0 })
+            // set from the level when it starts
+            .init_resource::<Arena>()
+            // the defaults, unless the TuningPlugin is added too
+            .init_resource::<Tuning>()
+            .init_resource::<CustomTuning>()
+            .init_resource::<Difficulty>()
+            .init_resource::<GameDifficulty>()
+            .init_resource::<Despawns>()
+            .add_stage_after(stage::EVENT_UPDATE, STATE_TRANSITION)
+            .add_stage_after(STATE_TRANSITION, STATE_HOOKS);
+        schedule::add_stages(app);
+        app.add_startup_system(start_game_system.system())
+            .add_system_to_stage(stage::PRE_UPDATE, difficulty_system.system())
+            .add_system_to_stage(STATE_TRANSITION, state_transition_system.system())
+            .add_system_to_stage(
+                STATE_HOOKS,
+                on_enter(GameState::Restarting, end_game_system.system()),
+            )
+            .add_system_to_stage(
+                STATE_HOOKS,
+                on_enter(GameState::Starting, start_game_system.system()),
+            )
+            .add_system_to_stage(
+                STATE_HOOKS,
+                on_enter(GameState::Countdown, start_countdown_system.system()),
+            )
+            .add_system(run_in(COUNTDOWN, countdown_system.system()))
+            .add_system_to_stage(CLEANUP, run_in(FADING, fade_out_system.system()))
+            .add_system_to_stage(DESPAWN, despawn_system.thread_local_system());
+    }
+}
+
+pub const PLAYING: &[GameState] = &[GameState::Playing];
+
+const COUNTDOWN: &[GameState] = &[GameState::Countdown];
+
+/// The paddle can already be lined up during the countdown
+pub const PADDLE_MOVING: &[GameState] = &[GameState::Countdown, GameState::Playing];
+
+/// Fading out stops while paused and while restarting
+pub const FADING: &[GameState] = &[
+    GameState::Title,
+    GameState::Starting,
+    GameState::Countdown,
+    GameState::Playing,
+    GameState::Win,
+    GameState::Lose,
+];
+
+/// Time left before the ball starts moving
+pub struct Countdown {
+    pub remaining: f32,
+}
+
+/// Balls left to serve once the one in play is lost
+pub struct Lives {
+    pub remaining: usize,
+}
+
+pub struct FadeOut {
+    pub fade_out_time: f32,
+    pub starting_color: Color,
+}
+
+pub struct Name(pub String);
+
+pub struct DespawnOnEnd;
+
+/// The one source of randomness in the game, seeded so that a run can be repeated. Each game
+/// starts again from its own seed, so that the seed shown at the end of a game repeats it.
+pub struct GameRng {
+    /// The seed of the game in progress
+    pub seed: u64,
+    pub rng: StdRng,
+    /// Whether a game has been started from `seed` yet
+    played: bool,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            played: false,
+        }
+    }
+
+    /// Start a game from the seed it will show. The first game is played from the seed the
+    /// `GameRng` was made with, and every game after that from a fresh one, drawn from the last
+    /// so that a replay draws the same.
+    fn start_game(&mut self) {
+        if self.played {
+            self.seed = self.rng.gen();
+        }
+        self.played = true;
+        self.rng = StdRng::seed_from_u64(self.seed);
+    }
+}
+
+/// Player preferences, changed from the options menu
+pub struct Options {
+    pub show_debug_info: bool,
+    /// Draw what the collision detection sees, see [`crate::debug_draw`]
+    pub debug_draw: bool,
+    pub ball_trail: bool,
+}
+
+/// Runs on entering [`GameState::Restarting`], clearing away the last game
+fn end_game_system(
+    mut state: ResMut<StateMachine>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut level_timer: ResMut<LevelTimer>,
+    mut combo: ResMut<Combo>,
+    mut pool: ResMut<MaterialPool>,
+    mut despawns: ResMut<Despawns>,
+    mut despawn_query: Query<(Entity, &DespawnOnEnd)>,
+) {
+    for (entity, _) in &mut despawn_query.iter() {
+        despawns.despawn(entity);
+    }
+    pool.clear_shared();
+    scoreboard.score = 0;
+    level_timer.elapsed = 0.0;
+    combo.hits = 0;
+    state.set(GameState::Starting);
+}
+
+/// Spawns the current level, or the saved game being continued, at startup and on entering
+/// [`GameState::Starting`]
+fn start_game_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut pool: ResMut<MaterialPool>,
+    mut rng: ResMut<GameRng>,
+    state: Res<StateMachine>,
+    tuning: Res<Tuning>,
+    levels: Res<Levels>,
+    current_level: Res<CurrentLevel>,
+    mut arena: ResMut<Arena>,
+    mut save_slot: ResMut<SaveSlot>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut level_timer: ResMut<LevelTimer>,
+    mut combo: ResMut<Combo>,
+    mut lives: ResMut<Lives>,
+) {
+    // the level spawned at startup is only there behind the title screen
+    if state.entered(GameState::Starting) {
+        rng.start_game();
+    }
+    let level = &levels.0[current_level.0];
+    *arena = level.arena.clone();
+    if let Some(game) = save_slot.pending.take() {
+        load_game(
+            &mut commands,
+            &mut materials,
+            &mut pool,
+            &arena,
+            game,
+            &mut scoreboard,
+            &mut level_timer,
+            &mut combo,
+            &mut lives,
+        );
+        return;
+    }
+    lives.remaining = tuning.lives.saturating_sub(1);
+    spawn_paddle(
+        &mut commands,
+        &mut materials,
+        Vec2::new(0.0, arena.paddle_y()),
+        tuning.paddle_width,
+        Color::BLACK,
+    );
+    serve_ball(&mut commands, &mut materials, &tuning, Color::WHITE);
+    spawn_bricks(
+        &mut commands,
+        &mut materials,
+        &mut pool,
+        &mut rng,
+        level,
+        &arena,
+    );
+}
+
+/// Runs on entering [`GameState::Countdown`]
+fn start_countdown_system(tuning: Res<Tuning>, mut countdown: ResMut<Countdown>) {
+    countdown.remaining = tuning.countdown_time;
+}
+
+fn countdown_system(
+    time: Res<Time>,
+    mut state: ResMut<StateMachine>,
+    mut countdown: ResMut<Countdown>,
+) {
+    countdown.remaining -= time.delta_seconds;
+    if countdown.remaining <= 0.0 {
+        state.set(GameState::Playing);
+    }
+}
+
+fn fade_out_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    tuning: Res<Tuning>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut pool: ResMut<MaterialPool>,
+    mut despawns: ResMut<Despawns>,
+    mut despawn_query: Query<(
+        Entity,
+        &mut FadeOut,
+        &mut Handle<ColorMaterial>,
+        Option<&Pooled>,
+    )>,
+) {
+    let rgb = Vec4::new(1.0, 1.0, 1.0, 0.0);
+    for (entity, mut fade_out, mut material_handle, pooled) in &mut despawn_query.iter() {
+        if pooled.is_none() {
+            // the material may be shared with other sprites, so fade one of its own instead
+            *material_handle = pool.take(&mut materials, fade_out.starting_color);
+            commands.insert_one(entity, Pooled);
+        }
+        fade_out.fade_out_time -= time.delta_seconds;
+        if fade_out.fade_out_time > 0.0 {
+            let material = materials.get_mut(material_handle).unwrap();
+            // let color = color_to_vec4(material.color);
+            let color = color_to_vec4(fade_out.starting_color);
+            material.color = (color * rgb
+                + Vec4::new(0.0, 0.0, 0.0, fade_out.fade_out_time / tuning.fade_out_time))
+            .into();
+        } else {
+            despawns.despawn(entity);
+        }
+    }
+}
+
+pub fn color_to_vec4(color: Color) -> Vec4 {
+    let color: [f32; 4] = color.into();
+    color.into()
+}
@@ -0,0 +1,32 @@
+//! Running the game without a window or a renderer, for tests and tools that run on machines
+//! without a GPU.
+
+use bevy::{
+    asset::AssetPlugin, core::CorePlugin, input::InputPlugin, prelude::*,
+    transform::TransformPlugin, type_registry::TypeRegistryPlugin, window::WindowPlugin,
+};
+
+/// The parts of Bevy the game needs, without a window or a renderer, to add in place of
+/// `add_default_plugins`.
+///
+/// Sprites and text are still spawned, they're just never drawn, so the HUD and debug plugins
+/// aren't worth adding. Nothing drives the frames either: call `App::update` to step the game, or
+/// add a `ScheduleRunnerPlugin`.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(TypeRegistryPlugin::default())
+            .add_plugin(CorePlugin::default())
+            .add_plugin(TransformPlugin::default())
+            .add_plugin(InputPlugin::default())
+            // for the window events and `Windows` resource the menus and replays use
+            .add_plugin(WindowPlugin {
+                add_primary_window: false,
+                ..Default::default()
+            })
+            .add_plugin(AssetPlugin::default())
+            // the colours of the ball, paddle and bricks are part of the game
+            .add_asset::<ColorMaterial>();
+    }
+}
@@ -0,0 +1,298 @@
+//! The local high score tables, one for each difficulty.
+//!
+//! The table lives in `highscores.ron` in the user's data directory. The file carries a version
+//! number, and a file that can't be read is moved aside to `highscores.ron.corrupt` instead of
+//! stopping the game. A file written by a newer version of the game is left untouched, and the
+//! table is kept in memory only.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{prelude::*, window::ReceivedCharacter};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bricks::{CurrentLevel, Levels},
+    data_file,
+    difficulty::{Difficulty, GameDifficulty},
+    menu::{Menu, MenuScreen},
+    replay::Replay,
+    schedule::INPUT,
+    scoring::{LevelTimer, Scoreboard},
+    state::{on_enter, on_exit, GameState, STATE_HOOKS},
+    write_data_file,
+};
+
+/// Bump this whenever [`HighScore`] changes shape
+const HIGH_SCORE_VERSION: u32 = 2;
+const MAX_HIGH_SCORES: usize = 10;
+const MAX_NAME_LENGTH: usize = 12;
+
+pub struct HighScorePlugin;
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(HighScores::load())
+            .init_resource::<NameEntry>()
+            .add_system_to_stage(
+                STATE_HOOKS,
+                on_enter(GameState::Win, check_high_score_system.system()),
+            )
+            .add_system_to_stage(
+                STATE_HOOKS,
+                on_enter(GameState::Lose, check_high_score_system.system()),
+            )
+            .add_system_to_stage(
+                STATE_HOOKS,
+                on_exit(GameState::Win, discard_high_score_system.system()),
+            )
+            .add_system_to_stage(
+                STATE_HOOKS,
+                on_exit(GameState::Lose, discard_high_score_system.system()),
+            )
+            .add_system_to_stage(INPUT, name_entry_system.system());
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighScore {
+    pub name: String,
+    pub score: usize,
+    /// The name of the level the score was made on
+    pub level: String,
+    pub seconds: f32,
+    /// When the score was made, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Scores from before there were difficulties were all made on Normal
+    #[serde(default)]
+    pub difficulty: Difficulty,
+}
+
+impl HighScore {
+    /// The day the score was made on, as `YYYY-MM-DD`
+    pub fn date(&self) -> String {
+        // days since 1970-01-01 to a civil date, from http://howardhinnant.github.io/date_algorithms.html
+        let days = (self.timestamp / 86_400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct HighScoreFile {
+    version: u32,
+    entries: Vec<HighScore>,
+}
+
+pub struct HighScores {
+    /// Where the table is saved, or `None` if it shouldn't be
+    path: Option<PathBuf>,
+    entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    /// Load the table from the user's data directory, starting afresh if there isn't one yet
+    pub fn load() -> Self {
+        let path = data_file("highscores.ron");
+        let contents = match path.as_ref().map(fs::read_to_string) {
+            Some(Ok(contents)) => contents,
+            _ => {
+                return Self {
+                    path,
+                    entries: Vec::new(),
+                }
+            }
+        };
+        let path = path.unwrap();
+        match ron::de::from_str::<HighScoreFile>(&contents) {
+            Ok(file) if file.version > HIGH_SCORE_VERSION => {
+                println!(
+                    "{} is from a newer version of the game, high scores won't be saved",
+                    path.display()
+                );
+                Self {
+                    path: None,
+                    entries: file.entries,
+                }
+            }
+            Ok(file) => {
+                let mut high_scores = Self {
+                    path: Some(path),
+                    entries: file.entries,
+                };
+                high_scores.tidy();
+                high_scores
+            }
+            Err(e) => {
+                let corrupt_path = path.with_extension("ron.corrupt");
+                println!(
+                    "could not read {} ({}), moving it to {}",
+                    path.display(),
+                    e,
+                    corrupt_path.display()
+                );
+                let _ = fs::rename(&path, &corrupt_path);
+                Self {
+                    path: Some(path),
+                    entries: Vec::new(),
+                }
+            }
+        }
+    }
+
+    /// The table for `difficulty`, best first
+    pub fn entries(&self, difficulty: Difficulty) -> impl Iterator<Item = &HighScore> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.difficulty == difficulty)
+    }
+
+    pub fn qualifies(&self, score: usize, difficulty: Difficulty) -> bool {
+        score > 0
+            && (self.entries(difficulty).count() < MAX_HIGH_SCORES
+                || self.entries(difficulty).any(|entry| entry.score < score))
+    }
+
+    pub fn insert(&mut self, entry: HighScore) {
+        self.entries.push(entry);
+        self.tidy();
+        self.save();
+    }
+
+    /// Keep the tables sorted, best first, and no longer than they should be
+    fn tidy(&mut self) {
+        // a stable sort, so that older scores stay ahead of equal newer ones
+        self.entries.sort_by(|a, b| b.score.cmp(&a.score));
+        let mut counts = HashMap::new();
+        self.entries.retain(|entry| {
+            let count = counts.entry(entry.difficulty).or_insert(0);
+            *count += 1;
+            *count <= MAX_HIGH_SCORES
+        });
+    }
+
+    fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+        let file = HighScoreFile {
+            version: HIGH_SCORE_VERSION,
+            entries: self.entries.clone(),
+        };
+        let result = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+            .and_then(|contents| write_data_file(path, &contents).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            println!("could not save high scores to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// A new high score, waiting for the player to type in their name
+#[derive(Default)]
+pub struct NameEntry {
+    entry: Option<HighScore>,
+    pub name: String,
+}
+
+impl NameEntry {
+    /// While a name is being typed in, the keyboard isn't used for anything else
+    pub fn active(&self) -> bool {
+        self.entry.is_some()
+    }
+
+    /// Take the new high score, with the name that was typed in
+    pub fn take(&mut self) -> Option<HighScore> {
+        let name = std::mem::replace(&mut self.name, String::new());
+        self.entry.take().map(|entry| HighScore {
+            name: if name.is_empty() { "???".into() } else { name },
+            ..entry
+        })
+    }
+
+    pub fn discard(&mut self) {
+        self.entry = None;
+        self.name.clear();
+    }
+}
+
+/// Runs on entering [`GameState::Win`] and [`GameState::Lose`], asking for a name if the score made the
+/// table
+fn check_high_score_system(
+    replay: Res<Replay>,
+    scoreboard: Res<Scoreboard>,
+    level_timer: Res<LevelTimer>,
+    levels: Res<Levels>,
+    current_level: Res<CurrentLevel>,
+    game_difficulty: Res<GameDifficulty>,
+    high_scores: Res<HighScores>,
+    mut name_entry: ResMut<NameEntry>,
+    mut menu: ResMut<Menu>,
+) {
+    name_entry.discard();
+    // a replay doesn't get to set high scores, whatever the table on this machine looks like
+    if let Replay::Playback(_) = *replay {
+        return;
+    }
+    // filed under the difficulty the game was played at, whatever the options say now
+    let difficulty = game_difficulty.0;
+    if high_scores.qualifies(scoreboard.score, difficulty) {
+        name_entry.entry = Some(HighScore {
+            name: String::new(),
+            score: scoreboard.score,
+            level: levels.0[current_level.0].name.clone(),
+            seconds: level_timer.elapsed,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+            difficulty,
+        });
+        menu.open(MenuScreen::NameEntry);
+    }
+}
+
+/// Runs on leaving [`GameState::Win`] and [`GameState::Lose`], in case the name was never entered
+fn discard_high_score_system(mut name_entry: ResMut<NameEntry>) {
+    name_entry.discard();
+}
+
+fn name_entry_system(
+    mut character_reader: Local<EventReader<ReceivedCharacter>>,
+    character_events: Res<Events<ReceivedCharacter>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut name_entry: ResMut<NameEntry>,
+) {
+    let characters: Vec<char> = character_reader
+        .iter(&character_events)
+        .map(|event| event.char)
+        .collect();
+    if !name_entry.active() {
+        return;
+    }
+    for character in characters {
+        if !character.is_control() && name_entry.name.chars().count() < MAX_NAME_LENGTH {
+            name_entry.name.push(character);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        name_entry.name.pop();
+    }
+}
@@ -0,0 +1,374 @@
+//! The score, timer, combo and lives shown during play, and the messages shown between games.
+
+use bevy::prelude::*;
+
+use crate::{
+    arena::ArenaView,
+    despawn::Despawns,
+    game::{Countdown, DespawnOnEnd, GameRng, Lives, FADING},
+    schedule::UI,
+    scoring::{BrickScored, Combo, LevelResult, LevelTimer, Scoreboard},
+    state::{run_in, GameState, StateMachine},
+    tuning::Tuning,
+};
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(setup_hud.system())
+            .add_system_to_stage(UI, scoreboard_system.system())
+            .add_system_to_stage(UI, level_timer_text_system.system())
+            .add_system_to_stage(UI, combo_text_system.system())
+            .add_system_to_stage(UI, lives_text_system.system())
+            .add_system_to_stage(UI, floating_score_system.system())
+            .add_system_to_stage(UI, run_in(FADING, floating_text_system.system()))
+            .add_system_to_stage(UI, score_breakdown_text_system.system())
+            .add_system_to_stage(UI, render_game_state_text_system.system())
+            .add_system_to_stage(UI, render_seed_text_system.system());
+    }
+}
+
+const FLOATING_TEXT_TIME: f32 = 1.0;
+
+struct Score;
+
+struct GameStateText;
+
+struct SeedText;
+
+struct LevelTimerText;
+
+struct ComboText;
+
+struct LivesText;
+
+struct ScoreBreakdownText;
+
+/// The "+N" text that floats up from a broken brick
+struct FloatingText {
+    remaining: f32,
+}
+
+fn setup_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    // this is now relative to the PROJECT_ROOT/assets directory, will panic if not found
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    commands
+        // scoreboard
+        .spawn(TextComponents {
+            text: Text {
+                font: font.clone(),
+                value: "".to_string(),
+                style: TextStyle {
+                    color: Color::rgb(0.2, 0.2, 0.8),
+                    font_size: 40.0,
+                },
+            },
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(5.0),
+                    left: Val::Px(5.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(Score)
+        // level timer
+        .spawn(TextComponents {
+            text: Text {
+                font: font.clone(),
+                value: "".to_string(),
+                style: TextStyle {
+                    color: Color::rgb(0.2, 0.2, 0.8),
+                    font_size: 40.0,
+                },
+            },
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(5.0),
+                    right: Val::Px(5.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(LevelTimerText)
+        // combo multiplier
+        .spawn(TextComponents {
+            text: Text {
+                font: font.clone(),
+                value: "".to_string(),
+                style: TextStyle {
+                    color: Color::rgb(0.8, 0.2, 0.2),
+                    font_size: 40.0,
+                },
+            },
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(45.0),
+                    right: Val::Px(5.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(ComboText)
+        // balls left
+        .spawn(TextComponents {
+            text: Text {
+                font: font.clone(),
+                value: "".to_string(),
+                style: TextStyle {
+                    color: Color::rgb(0.2, 0.2, 0.8),
+                    font_size: 40.0,
+                },
+            },
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(85.0),
+                    right: Val::Px(5.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(LivesText)
+        // the messages between games, centred in the top half of the window above the menu
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(0.0),
+                    left: Val::Px(0.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Percent(55.0)),
+                // UI is laid out bottom to top, so reverse it to list the messages top down
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                // game state text
+                .spawn(message(&font, 100.0))
+                .with(GameStateText)
+                // score breakdown, shown when a level is complete
+                .spawn(message(&font, 30.0))
+                .with(ScoreBreakdownText)
+                // seed text, shown when the game is over so that the run can be repeated
+                .spawn(message(&font, 40.0))
+                .with(SeedText);
+        });
+}
+
+/// A line of white text in the middle of the window
+fn message(font: &Handle<Font>, font_size: f32) -> TextComponents {
+    TextComponents {
+        text: Text {
+            font: font.clone(),
+            value: "".to_string(),
+            style: TextStyle {
+                color: Color::rgb(1.0, 1.0, 1.0),
+                font_size,
+            },
+        },
+        ..Default::default()
+    }
+}
+
+fn scoreboard_system(scoreboard: Res<Scoreboard>, mut query: Query<(&mut Text, &Score)>) {
+    for (mut text, _score_marker) in &mut query.iter() {
+        let text_value = format!("Score: {}", scoreboard.score);
+        if text.value != text_value {
+            text.value = text_value;
+        }
+    }
+}
+
+fn level_timer_text_system(
+    level_timer: Res<LevelTimer>,
+    mut query: Query<(&mut Text, &LevelTimerText)>,
+) {
+    for (mut text, _level_timer_marker) in &mut query.iter() {
+        let text_value = format!(
+            "Time: {}:{:04.1}",
+            (level_timer.elapsed / 60.0) as usize,
+            level_timer.elapsed % 60.0
+        );
+        if text.value != text_value {
+            text.value = text_value;
+        }
+    }
+}
+
+fn combo_text_system(
+    combo: Res<Combo>,
+    tuning: Res<Tuning>,
+    mut query: Query<(&mut Text, &ComboText)>,
+) {
+    let multiplier = combo.multiplier(tuning.max_combo_multiplier);
+    for (mut text, _combo_marker) in &mut query.iter() {
+        let text_value = if multiplier > 1 {
+            format!("Combo x{}", multiplier)
+        } else {
+            "".to_string()
+        };
+        if text.value != text_value {
+            text.value = text_value;
+        }
+    }
+}
+
+fn lives_text_system(lives: Res<Lives>, mut query: Query<(&mut Text, &LivesText)>) {
+    for (mut text, _lives_marker) in &mut query.iter() {
+        let text_value = if lives.remaining > 0 {
+            format!("Balls: +{}", lives.remaining)
+        } else {
+            "".to_string()
+        };
+        if text.value != text_value {
+            text.value = text_value;
+        }
+    }
+}
+
+/// Spawns a "+N" where each brick was broken
+fn floating_score_system(
+    mut commands: Commands,
+    mut brick_scored_reader: Local<EventReader<BrickScored>>,
+    brick_scored_events: Res<Events<BrickScored>>,
+    asset_server: Res<AssetServer>,
+    view: Res<ArenaView>,
+    windows: Res<Windows>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    for scored in brick_scored_reader.iter(&brick_scored_events) {
+        let position = view.to_window(scored.position, window);
+        let (left, top) = (position.x(), position.y());
+        commands
+            .spawn(TextComponents {
+                text: Text {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    value: format!("+{}", scored.points),
+                    style: TextStyle {
+                        color: Color::rgb(1.0, 1.0, 1.0),
+                        font_size: 30.0,
+                    },
+                },
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: Val::Px(top),
+                        left: Val::Px(left),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with(FloatingText {
+                remaining: FLOATING_TEXT_TIME,
+            })
+            .with(DespawnOnEnd);
+    }
+}
+
+/// Floats the "+N" text upwards while fading it out
+fn floating_text_system(
+    time: Res<Time>,
+    mut despawns: ResMut<Despawns>,
+    mut query: Query<(Entity, &mut FloatingText, &mut Style, &mut Text)>,
+) {
+    for (entity, mut floating_text, mut style, mut text) in &mut query.iter() {
+        floating_text.remaining -= time.delta_seconds;
+        if floating_text.remaining > 0.0 {
+            if let Val::Px(top) = style.position.top {
+                style.position.top = Val::Px(top - 40.0 * time.delta_seconds);
+            }
+            text.style.color.a = floating_text.remaining / FLOATING_TEXT_TIME;
+        } else {
+            despawns.despawn(entity);
+        }
+    }
+}
+
+fn score_breakdown_text_system(
+    state: Res<StateMachine>,
+    level_result: Res<LevelResult>,
+    mut query: Query<(&mut Text, &ScoreBreakdownText)>,
+) {
+    for (mut text, _score_breakdown_marker) in &mut query.iter() {
+        let text_value = match state.current() {
+            GameState::Win => format!(
+                "Bricks: {}  +  Time bonus ({:.1}s): {}  =  {}",
+                level_result.brick_score,
+                level_result.seconds,
+                level_result.time_bonus,
+                level_result.brick_score + level_result.time_bonus
+            ),
+            _ => "".to_string(),
+        };
+        if text.value != text_value {
+            text.value = text_value;
+        }
+    }
+}
+
+fn render_game_state_text_system(
+    state: Res<StateMachine>,
+    countdown: Res<Countdown>,
+    mut query: Query<(&mut Text, &GameStateText)>,
+) {
+    for (mut text, _game_state_text) in &mut query.iter() {
+        let text_value = match state.current() {
+            GameState::Title => "BREAKOUT".to_string(),
+            GameState::Starting => "Press Space to start".to_string(),
+            GameState::Countdown => format!("{:.0}", countdown.remaining.ceil()),
+            GameState::Playing => "".to_string(),
+            GameState::Restarting => "".to_string(),
+            GameState::Paused => "PAUSED".to_string(),
+            GameState::Win => "YOU WIN! :D".to_string(),
+            GameState::Lose => "YOU LOSE :(".to_string(),
+        };
+        if text.value != text_value {
+            text.value = text_value;
+        }
+    }
+}
+
+fn render_seed_text_system(
+    state: Res<StateMachine>,
+    rng: Res<GameRng>,
+    mut query: Query<(&mut Text, &SeedText)>,
+) {
+    for (mut text, _seed_text) in &mut query.iter() {
+        let text_value = match state.current() {
+            GameState::Win | GameState::Lose => format!("Seed: {}", rng.seed),
+            _ => "".to_string(),
+        };
+        if text.value != text_value {
+            text.value = text_value;
+        }
+    }
+}
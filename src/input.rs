@@ -0,0 +1,199 @@
+//! Starting, pausing and restarting the game from the keyboard, and key combos.
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{
+    highscore::NameEntry,
+    schedule::INPUT,
+    state::{GameState, StateMachine},
+};
+
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_to_stage(INPUT, start_pause_game_system.system());
+    }
+}
+
+fn start_pause_game_system(
+    mut state: ResMut<StateMachine>,
+    name_entry: Res<NameEntry>,
+    keyboard_input: Res<Input<KeyCode>>,
+) {
+    // the keys are being used to type in a name
+    if name_entry.active() {
+        return;
+    }
+    if keyboard_input.just_released(KeyCode::Space) {
+        let next = match state.current() {
+            GameState::Title => None,
+            GameState::Starting => Some(GameState::Countdown),
+            GameState::Countdown => Some(GameState::Paused),
+            GameState::Restarting => None,
+            GameState::Playing => Some(GameState::Paused),
+            GameState::Paused => Some(GameState::Countdown),
+            GameState::Win => Some(GameState::Restarting),
+            GameState::Lose => Some(GameState::Restarting),
+        };
+        if let Some(next) = next {
+            state.set(next);
+        }
+    } else if keyboard_input.just_released(KeyCode::R) && state.current() != GameState::Title {
+        state.set(GameState::Restarting);
+    }
+}
+
+/// A key press (with or without modifiers)
+#[derive(Clone)]
+pub struct Keypress {
+    key: KeyCode,
+    modifiers: HashSet<KeyCode>,
+}
+
+impl Keypress {
+    /// Register a key press (any key) - takes one argument, a [`KeyCode`]
+    pub fn new(key: KeyCode) -> Self {
+        Self {
+            key,
+            modifiers: HashSet::new(),
+        }
+    }
+    /// Add a modifier (any key) - takes one argument, a [`KeyCode`]
+    #[allow(dead_code)]
+    pub fn with_modifier(&mut self, modifier: KeyCode) -> &mut Self {
+        self.modifiers.insert(modifier);
+        self
+    }
+    /// Returns true if this key was just pressed, and all registered modifiers are currently pressed.
+    /// Takes one argument - pass it a reference to the keyboard input resource (`&Res<Input<KeyCode>>`)
+    fn just_pressed(&self, input: &Res<Input<KeyCode>>) -> bool {
+        if input.just_pressed(self.key) {
+            for &modifier in &self.modifiers {
+                if !input.pressed(modifier) {
+                    return false;
+                }
+            }
+            true
+        } else {
+            false
+        }
+    }
+    /// Returns true if this key was just released, and all registered modifiers are currently pressed.
+    /// Takes one argument - pass it a reference to the keyboard input resource (`&Res<Input<KeyCode>>`)
+    fn just_released(&self, input: &Res<Input<KeyCode>>) -> bool {
+        if input.just_released(self.key) {
+            for &modifier in &self.modifiers {
+                if !input.pressed(modifier) {
+                    return false;
+                }
+            }
+            true
+        } else {
+            false
+        }
+    }
+}
+
+enum WaitForKey {
+    Press,
+    Release,
+}
+
+pub struct KeyCombo {
+    keypress_sequence: Vec<Keypress>,
+    max_wait_for_release: f32,
+    wait_for_release_timer: f32,
+    max_wait_for_press: f32,
+    wait_for_press_timer: f32,
+    waiting_for: WaitForKey,
+    index: usize,
+    done_on_press: bool,
+}
+
+// if index is at 0, this thing waits for the first key in the sequence
+// when this key is just pressed, it starts the
+impl KeyCombo {
+    /// Register a key combo
+    ///
+    /// # Arguments
+    ///
+    /// * `keypress_sequence`: what sequence of [key presses](Keypress) triggers a "done"
+    /// * `max_wait_for_press`: how long to allow between key presses
+    /// * `max_wait_for_release`: how long to allow a key to be held down to be treated as a key press
+    /// * `done_on_press`: if true, this ends the key combo when the last key is pressed, not released
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bevy::prelude::KeyCode;
+    /// use my_bevy_game::input::{KeyCombo, Keypress};
+    ///
+    /// let key_combo = KeyCombo::new(
+    ///     vec![Keypress::new(KeyCode::Left), Keypress::new(KeyCode::Left)],
+    ///     0.5,
+    ///     0.25,
+    ///     false,
+    /// );
+    /// ```
+    pub fn new(
+        keypress_sequence: Vec<Keypress>,
+        max_wait_for_press: f32,
+        max_wait_for_release: f32,
+        done_on_press: bool,
+    ) -> Self {
+        Self {
+            keypress_sequence,
+            max_wait_for_release,
+            wait_for_release_timer: 0.0,
+            max_wait_for_press,
+            wait_for_press_timer: 0.0,
+            waiting_for: WaitForKey::Press,
+            index: 0,
+            done_on_press,
+        }
+    }
+    pub fn reset(&mut self) {
+        self.wait_for_release_timer = 0.0;
+        self.wait_for_press_timer = 0.0;
+        self.waiting_for = WaitForKey::Press;
+        self.index = 0;
+    }
+    /// Check if a key combo has been fully entered
+    pub fn done(&mut self, input: &Res<Input<KeyCode>>, delta_time: f32) -> bool {
+        let current_key = &self.keypress_sequence[self.index];
+        let mut reset = false;
+        match self.waiting_for {
+            WaitForKey::Press => {
+                self.wait_for_press_timer += delta_time;
+                if current_key.just_pressed(input) {
+                    self.waiting_for = WaitForKey::Release;
+                } else if self.wait_for_press_timer >= self.max_wait_for_press {
+                    reset = true;
+                }
+            }
+            WaitForKey::Release => {
+                self.wait_for_release_timer += delta_time;
+                if current_key.just_released(input) {
+                    self.index += 1;
+                    self.waiting_for = WaitForKey::Press;
+                } else if self.wait_for_release_timer >= self.max_wait_for_release {
+                    reset = true;
+                }
+            }
+        }
+        if reset {
+            self.reset();
+        }
+        if (!self.done_on_press && self.index >= self.keypress_sequence.len())
+            || (self.done_on_press && self.index >= self.keypress_sequence.len() - 1)
+        {
+            self.reset();
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,48 @@
+#![feature(external_doc)]
+#![doc(include = "../README.md")]
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub mod arena;
+pub mod ball;
+pub mod bricks;
+pub mod cli;
+pub mod collision;
+pub mod debug;
+pub mod debug_draw;
+pub mod debug_lines;
+pub mod despawn;
+pub mod difficulty;
+pub mod game;
+pub mod headless;
+pub mod highscore;
+pub mod hud;
+pub mod input;
+pub mod materials;
+pub mod menu;
+pub mod paddle;
+pub mod replay;
+pub mod save;
+pub mod schedule;
+pub mod scoring;
+pub mod state;
+pub mod tuning;
+
+/// Where a file the game keeps between runs, like the high scores, lives
+fn data_file(name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("bevy-breakout").join(name))
+}
+
+/// Write a file in the data directory, creating the directory if need be. The file is written to
+/// a temporary file first, so that a crash can't leave half a file behind.
+fn write_data_file(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temporary_path = path.with_extension("tmp");
+    fs::write(&temporary_path, contents)?;
+    fs::rename(&temporary_path, path)
+}
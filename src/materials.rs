@@ -0,0 +1,82 @@
+//! Sharing and recycling `ColorMaterial`s, so that the number of materials stays about the same
+//! however long the game is played.
+//!
+//! Sprites whose colour never changes share one material per colour, see
+//! [`MaterialPool::shared`]. Sprites that fade out need a material of their own to fade, so they
+//! borrow one with [`MaterialPool::take`], which is given back when they're despawned and handed
+//! out again to the next sprite that fades.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+/// Marks an entity whose material was taken from the [`MaterialPool`], and has to be given back
+/// when the entity is despawned
+pub struct Pooled;
+
+#[derive(Default)]
+pub struct MaterialPool {
+    shared: HashMap<[u8; 4], Handle<ColorMaterial>>,
+    /// Materials given back, ready to be taken again
+    free: Vec<Handle<ColorMaterial>>,
+    /// How many materials have been taken and not given back
+    taken: usize,
+}
+
+/// Colours that look the same share a material
+fn key(color: Color) -> [u8; 4] {
+    let to_u8 = |channel: f32| (channel.max(0.0).min(1.0) * 255.0).round() as u8;
+    [
+        to_u8(color.r),
+        to_u8(color.g),
+        to_u8(color.b),
+        to_u8(color.a),
+    ]
+}
+
+impl MaterialPool {
+    /// A material of `color` shared by every sprite of that colour, so it mustn't be changed
+    pub fn shared(
+        &mut self,
+        materials: &mut Assets<ColorMaterial>,
+        color: Color,
+    ) -> Handle<ColorMaterial> {
+        self.shared
+            .entry(key(color))
+            .or_insert_with(|| materials.add(color.into()))
+            .clone()
+    }
+
+    /// A material of `color` for one sprite to change as it likes, until it is given back with
+    /// [`MaterialPool::give_back`]. The sprite should be marked [`Pooled`].
+    pub fn take(
+        &mut self,
+        materials: &mut Assets<ColorMaterial>,
+        color: Color,
+    ) -> Handle<ColorMaterial> {
+        self.taken += 1;
+        match self.free.pop() {
+            Some(handle) => {
+                materials.get_mut(&handle).unwrap().color = color;
+                handle
+            }
+            None => materials.add(color.into()),
+        }
+    }
+
+    pub fn give_back(&mut self, handle: Handle<ColorMaterial>) {
+        self.taken = self.taken.saturating_sub(1);
+        self.free.push(handle);
+    }
+
+    /// Forget the shared materials, which are freed once nothing uses them. Called between games,
+    /// so that the colours of old levels don't pile up.
+    pub fn clear_shared(&mut self) {
+        self.shared.clear();
+    }
+
+    /// How many materials the pool is holding on to, as `(shared, taken, free)`
+    pub fn counts(&self) -> (usize, usize, usize) {
+        (self.shared.len(), self.taken, self.free.len())
+    }
+}
@@ -0,0 +1,544 @@
+//! The title, level select, options, high score, pause and game over menus.
+//!
+//! Which menu is on screen follows from the [`GameState`] (plus an optional sub-menu such as the
+//! options), and the menu is rebuilt whenever that, or the text of one of its items, changes.
+//! Keyboard and mouse input both turn into [`MenuAction`] events, so that the replay can record
+//! and play back what was chosen rather than how it was chosen.
+
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    window::{WindowFocused, WindowResized},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bricks::{CurrentLevel, Levels},
+    despawn::Despawns,
+    difficulty::Difficulty,
+    game::Options,
+    highscore::{HighScores, NameEntry},
+    replay::Replay,
+    save::SaveSlot,
+    schedule::{INPUT, UI},
+    state::{on_exit, GameState, StateMachine, STATE_HOOKS},
+};
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Menu>()
+            .init_resource::<MenuMaterials>()
+            .add_event::<MenuAction>()
+            .add_system_to_stage(
+                STATE_HOOKS,
+                on_exit(GameState::Title, close_menu_system.system()),
+            )
+            .add_system_to_stage(
+                STATE_HOOKS,
+                on_exit(GameState::Paused, close_menu_system.system()),
+            )
+            .add_system_to_stage(INPUT, menu_keyboard_system.system())
+            .add_system_to_stage(INPUT, menu_mouse_system.system())
+            .add_system_to_stage(INPUT, window_focus_system.system())
+            .add_system(menu_action_system.system())
+            .add_system_to_stage(UI, menu_render_system.system())
+            .add_system_to_stage(UI, menu_highlight_system.system());
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MenuScreen {
+    Title,
+    LevelSelect,
+    Options,
+    HighScores,
+    NameEntry,
+    Pause,
+    GameOver,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MenuAction {
+    Play,
+    OpenLevelSelect,
+    SelectLevel(usize),
+    OpenOptions,
+    ToggleDebugInfo,
+    ToggleDebugDraw,
+    ToggleBallTrail,
+    CycleDifficulty,
+    OpenHighScores,
+    SaveHighScore,
+    /// Handled by the [`SavePlugin`](crate::save::SavePlugin)
+    Continue,
+    Back,
+    Pause,
+    Resume,
+    Restart,
+    /// Handled by the [`SavePlugin`](crate::save::SavePlugin)
+    SaveAndQuit,
+    QuitToTitle,
+    Quit,
+}
+
+#[derive(Default)]
+pub struct Menu {
+    /// A screen opened on top of the current one, e.g. the options from the pause menu
+    submenu: Option<MenuScreen>,
+    selected: usize,
+    /// What is on screen right now, so that the menu is only rebuilt when it changes
+    rendered: Option<(MenuScreen, Vec<String>)>,
+}
+
+impl Menu {
+    /// Open `screen` on top of whatever the game state shows
+    pub fn open(&mut self, screen: MenuScreen) {
+        self.submenu = Some(screen);
+    }
+
+    fn screen(&self, state: &StateMachine) -> Option<MenuScreen> {
+        self.submenu.or(match state.current() {
+            GameState::Title => Some(MenuScreen::Title),
+            GameState::Paused => Some(MenuScreen::Pause),
+            GameState::Win | GameState::Lose => Some(MenuScreen::GameOver),
+            _ => None,
+        })
+    }
+}
+
+struct MenuRoot;
+
+struct MenuItem {
+    index: usize,
+    action: MenuAction,
+}
+
+struct MenuMaterials {
+    background: Handle<ColorMaterial>,
+    normal: Handle<ColorMaterial>,
+    selected: Handle<ColorMaterial>,
+    font: Handle<Font>,
+}
+
+impl FromResources for MenuMaterials {
+    fn from_resources(resources: &Resources) -> Self {
+        let mut materials = resources.get_mut::<Assets<ColorMaterial>>().unwrap();
+        let asset_server = resources.get::<AssetServer>().unwrap();
+        MenuMaterials {
+            background: materials.add(Color::NONE.into()),
+            normal: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+            selected: materials.add(Color::rgb(0.2, 0.2, 0.8).into()),
+            font: asset_server.load("FiraSans-Bold.ttf"),
+        }
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+fn menu_items(
+    screen: MenuScreen,
+    levels: &Levels,
+    current_level: &CurrentLevel,
+    options: &Options,
+    difficulty: Difficulty,
+    name_entry: &NameEntry,
+    high_scores: &HighScores,
+    save_slot: &SaveSlot,
+) -> Vec<(String, MenuAction)> {
+    match screen {
+        MenuScreen::Title => {
+            let mut items = Vec::new();
+            if save_slot.exists() {
+                items.push(("Continue".into(), MenuAction::Continue));
+            }
+            items.extend(vec![
+                ("Play".into(), MenuAction::Play),
+                ("Level select".into(), MenuAction::OpenLevelSelect),
+                ("High scores".into(), MenuAction::OpenHighScores),
+                ("Options".into(), MenuAction::OpenOptions),
+                ("Quit".into(), MenuAction::Quit),
+            ]);
+            items
+        }
+        MenuScreen::LevelSelect => {
+            let mut items: Vec<(String, MenuAction)> = levels
+                .0
+                .iter()
+                .enumerate()
+                .map(|(index, level)| {
+                    let label = if index == current_level.0 {
+                        format!("> {}", level.name)
+                    } else {
+                        level.name.clone()
+                    };
+                    (label, MenuAction::SelectLevel(index))
+                })
+                .collect();
+            items.push(("Back".into(), MenuAction::Back));
+            items
+        }
+        MenuScreen::Options => vec![
+            (
+                format!("Debug info: {}", on_off(options.show_debug_info)),
+                MenuAction::ToggleDebugInfo,
+            ),
+            (
+                format!("Debug draw: {}", on_off(options.debug_draw)),
+                MenuAction::ToggleDebugDraw,
+            ),
+            (
+                format!("Ball trail: {}", on_off(options.ball_trail)),
+                MenuAction::ToggleBallTrail,
+            ),
+            (
+                format!("Difficulty: {}", difficulty.name()),
+                MenuAction::CycleDifficulty,
+            ),
+            ("Back".into(), MenuAction::Back),
+        ],
+        MenuScreen::HighScores => {
+            // every row goes back, there is nothing else to do with them
+            let mut items: Vec<(String, MenuAction)> = high_scores
+                .entries(difficulty)
+                .enumerate()
+                .map(|(index, entry)| {
+                    let label = format!(
+                        "{}. {}  {}  {}  {:.1}s  {}",
+                        index + 1,
+                        entry.name,
+                        entry.score,
+                        entry.level,
+                        entry.seconds,
+                        entry.date()
+                    );
+                    (label, MenuAction::Back)
+                })
+                .collect();
+            items.insert(
+                0,
+                (
+                    format!("{} difficulty", difficulty.name()),
+                    MenuAction::Back,
+                ),
+            );
+            items.push(("Back".into(), MenuAction::Back));
+            items
+        }
+        MenuScreen::NameEntry => vec![
+            (
+                format!("New high score! Name: {}_", name_entry.name),
+                MenuAction::SaveHighScore,
+            ),
+            ("Skip".into(), MenuAction::Back),
+        ],
+        MenuScreen::Pause => vec![
+            ("Resume".into(), MenuAction::Resume),
+            ("Restart".into(), MenuAction::Restart),
+            ("Options".into(), MenuAction::OpenOptions),
+            ("Save and quit".into(), MenuAction::SaveAndQuit),
+            ("Quit to title".into(), MenuAction::QuitToTitle),
+        ],
+        MenuScreen::GameOver => vec![
+            ("Retry".into(), MenuAction::Restart),
+            ("Menu".into(), MenuAction::QuitToTitle),
+        ],
+    }
+}
+
+/// `Up` and `Down` move the selection, `Enter` picks it, and `Escape` goes back (or pauses)
+fn menu_keyboard_system(
+    replay: Res<Replay>,
+    state: Res<StateMachine>,
+    levels: Res<Levels>,
+    current_level: Res<CurrentLevel>,
+    options: Res<Options>,
+    difficulty: Res<Difficulty>,
+    name_entry: Res<NameEntry>,
+    high_scores: Res<HighScores>,
+    save_slot: Res<SaveSlot>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut menu: ResMut<Menu>,
+    mut actions: ResMut<Events<MenuAction>>,
+) {
+    // during playback the recorded actions drive the menus
+    if let Replay::Playback(_) = *replay {
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        if menu.submenu.is_some() {
+            actions.send(MenuAction::Back);
+        } else if matches!(state.current(), GameState::Countdown | GameState::Playing) {
+            actions.send(MenuAction::Pause);
+        } else if state.current() == GameState::Paused {
+            actions.send(MenuAction::Resume);
+        }
+        return;
+    }
+    if let Some(screen) = menu.screen(&state) {
+        let items = menu_items(
+            screen,
+            &levels,
+            &current_level,
+            &options,
+            *difficulty,
+            &name_entry,
+            &high_scores,
+            &save_slot,
+        );
+        if keyboard_input.just_pressed(KeyCode::Up) {
+            menu.selected = (menu.selected + items.len() - 1) % items.len();
+        }
+        if keyboard_input.just_pressed(KeyCode::Down) {
+            menu.selected = (menu.selected + 1) % items.len();
+        }
+        if keyboard_input.just_pressed(KeyCode::Return) {
+            if let Some((_, action)) = items.get(menu.selected) {
+                actions.send(*action);
+            }
+        }
+    }
+}
+
+/// Pauses the game when the window loses focus or is minimised. Regaining focus doesn't resume
+/// it, the player has to do that themselves.
+fn window_focus_system(
+    replay: Res<Replay>,
+    state: Res<StateMachine>,
+    mut focused_reader: Local<EventReader<WindowFocused>>,
+    mut resized_reader: Local<EventReader<WindowResized>>,
+    focused_events: Res<Events<WindowFocused>>,
+    resized_events: Res<Events<WindowResized>>,
+    mut actions: ResMut<Events<MenuAction>>,
+) {
+    let lost_focus = focused_reader
+        .iter(&focused_events)
+        .any(|event| !event.focused);
+    let minimised = resized_reader
+        .iter(&resized_events)
+        .any(|event| event.width == 0 || event.height == 0);
+    // during playback the recorded pause is sent instead
+    if let Replay::Playback(_) = *replay {
+        return;
+    }
+    if (lost_focus || minimised)
+        && matches!(state.current(), GameState::Countdown | GameState::Playing)
+    {
+        actions.send(MenuAction::Pause);
+    }
+}
+
+/// Hovering over an item selects it, and clicking it picks it
+fn menu_mouse_system(
+    replay: Res<Replay>,
+    mut menu: ResMut<Menu>,
+    mut actions: ResMut<Events<MenuAction>>,
+    mut interaction_query: Query<(Mutated<Interaction>, &MenuItem)>,
+) {
+    if let Replay::Playback(_) = *replay {
+        return;
+    }
+    for (interaction, item) in &mut interaction_query.iter() {
+        match *interaction {
+            Interaction::Clicked => {
+                menu.selected = item.index;
+                actions.send(item.action);
+            }
+            Interaction::Hovered => {
+                menu.selected = item.index;
+            }
+            Interaction::None => {}
+        }
+    }
+}
+
+fn menu_action_system(
+    mut action_reader: Local<EventReader<MenuAction>>,
+    actions: Res<Events<MenuAction>>,
+    mut app_exit_events: ResMut<Events<AppExit>>,
+    mut menu: ResMut<Menu>,
+    mut state: ResMut<StateMachine>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut options: ResMut<Options>,
+    mut difficulty: ResMut<Difficulty>,
+    mut name_entry: ResMut<NameEntry>,
+    mut high_scores: ResMut<HighScores>,
+) {
+    for action in action_reader.iter(&actions) {
+        match *action {
+            MenuAction::Play => {
+                menu.submenu = None;
+                state.set(GameState::Restarting);
+            }
+            MenuAction::OpenLevelSelect => menu.submenu = Some(MenuScreen::LevelSelect),
+            MenuAction::SelectLevel(index) => {
+                current_level.0 = index;
+                menu.submenu = None;
+                state.set(GameState::Restarting);
+            }
+            MenuAction::OpenOptions => menu.submenu = Some(MenuScreen::Options),
+            MenuAction::ToggleDebugInfo => options.show_debug_info = !options.show_debug_info,
+            MenuAction::ToggleDebugDraw => options.debug_draw = !options.debug_draw,
+            MenuAction::ToggleBallTrail => options.ball_trail = !options.ball_trail,
+            MenuAction::CycleDifficulty => *difficulty = difficulty.next(),
+            MenuAction::OpenHighScores => menu.submenu = Some(MenuScreen::HighScores),
+            MenuAction::SaveHighScore => {
+                if let Some(entry) = name_entry.take() {
+                    high_scores.insert(entry);
+                }
+                menu.submenu = Some(MenuScreen::HighScores);
+            }
+            MenuAction::Continue | MenuAction::SaveAndQuit => menu.submenu = None,
+            MenuAction::Back => {
+                if menu.submenu == Some(MenuScreen::NameEntry) {
+                    name_entry.discard();
+                }
+                menu.submenu = None;
+            }
+            MenuAction::Pause => state.set(GameState::Paused),
+            MenuAction::Resume => state.set(GameState::Countdown),
+            MenuAction::Restart => {
+                menu.submenu = None;
+                state.set(GameState::Restarting);
+            }
+            MenuAction::QuitToTitle => {
+                menu.submenu = None;
+                state.set(GameState::Title);
+            }
+            MenuAction::Quit => app_exit_events.send(AppExit),
+        }
+    }
+}
+
+/// Runs on leaving a state with a menu, so that a sub-menu opened there doesn't stay open
+fn close_menu_system(mut menu: ResMut<Menu>) {
+    menu.submenu = None;
+}
+
+/// Rebuilds the menu's UI whenever the screen or the text of its items changes
+fn menu_render_system(
+    mut commands: Commands,
+    state: Res<StateMachine>,
+    levels: Res<Levels>,
+    current_level: Res<CurrentLevel>,
+    options: Res<Options>,
+    difficulty: Res<Difficulty>,
+    name_entry: Res<NameEntry>,
+    high_scores: Res<HighScores>,
+    save_slot: Res<SaveSlot>,
+    menu_materials: Res<MenuMaterials>,
+    mut menu: ResMut<Menu>,
+    mut despawns: ResMut<Despawns>,
+    mut root_query: Query<(Entity, &MenuRoot)>,
+) {
+    let screen = menu.screen(&state);
+    let items = screen.map(|screen| {
+        menu_items(
+            screen,
+            &levels,
+            &current_level,
+            &options,
+            *difficulty,
+            &name_entry,
+            &high_scores,
+            &save_slot,
+        )
+    });
+    let wanted = match (screen, &items) {
+        (Some(screen), Some(items)) => Some((
+            screen,
+            items.iter().map(|(label, _)| label.clone()).collect(),
+        )),
+        _ => None,
+    };
+    if menu.rendered == wanted {
+        return;
+    }
+    if menu.rendered.as_ref().map(|(screen, _)| *screen) != screen {
+        menu.selected = 0;
+    }
+    menu.rendered = wanted;
+
+    for (entity, _menu_root) in &mut root_query.iter() {
+        despawns.despawn_recursive(entity);
+    }
+    if let Some(items) = items {
+        menu.selected = menu.selected.min(items.len() - 1);
+        commands
+            .spawn(NodeComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    // across the window below the middle, under the HUD's messages
+                    position: Rect {
+                        top: Val::Percent(55.0),
+                        left: Val::Px(0.0),
+                        ..Default::default()
+                    },
+                    size: Size::new(Val::Percent(100.0), Val::Auto),
+                    // UI is laid out bottom to top, so reverse it to list the items top down
+                    flex_direction: FlexDirection::ColumnReverse,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                material: menu_materials.background.clone(),
+                ..Default::default()
+            })
+            .with(MenuRoot)
+            .with_children(|parent| {
+                for (index, (label, action)) in items.into_iter().enumerate() {
+                    parent
+                        .spawn(ButtonComponents {
+                            style: Style {
+                                // high score rows can be wider than the rest
+                                min_size: Size::new(Val::Px(320.0), Val::Px(50.0)),
+                                margin: Rect::all(Val::Px(4.0)),
+                                padding: Rect::all(Val::Px(8.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..Default::default()
+                            },
+                            material: menu_materials.normal.clone(),
+                            ..Default::default()
+                        })
+                        .with(MenuItem { index, action })
+                        .with_children(|parent| {
+                            parent.spawn(TextComponents {
+                                text: Text {
+                                    font: menu_materials.font.clone(),
+                                    value: label,
+                                    style: TextStyle {
+                                        color: Color::rgb(1.0, 1.0, 1.0),
+                                        font_size: 32.0,
+                                    },
+                                },
+                                ..Default::default()
+                            });
+                        });
+                }
+            });
+    }
+}
+
+fn menu_highlight_system(
+    menu: Res<Menu>,
+    menu_materials: Res<MenuMaterials>,
+    mut query: Query<(&MenuItem, &mut Handle<ColorMaterial>)>,
+) {
+    for (item, mut material) in &mut query.iter() {
+        let wanted = if item.index == menu.selected {
+            &menu_materials.selected
+        } else {
+            &menu_materials.normal
+        };
+        if *material != *wanted {
+            *material = wanted.clone();
+        }
+    }
+}
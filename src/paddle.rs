@@ -0,0 +1,124 @@
+//! The paddle, moved with the arrow keys, with a double tap to dash.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    arena::Arena,
+    collision::{Bounce, Collider, ColorTransfer, ResetsCombo},
+    game::{DespawnOnEnd, Name, PADDLE_MOVING},
+    input::{KeyCombo, Keypress},
+    schedule::MOVEMENT,
+    state::run_in,
+    tuning::Tuning,
+};
+
+pub struct PaddlePlugin;
+
+impl Plugin for PaddlePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_to_stage(
+            MOVEMENT,
+            run_in(PADDLE_MOVING, paddle_movement_system.system()),
+        );
+    }
+}
+
+pub struct Paddle;
+
+#[derive(Eq, PartialEq, Hash, Debug)]
+enum Handlers {
+    DoubleTapLeft,
+    DoubleTapRight,
+}
+
+pub fn spawn_paddle(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    position: Vec2,
+    width: f32,
+    color: Color,
+) {
+    commands
+        .spawn(SpriteComponents {
+            material: materials.add(color.into()),
+            transform: Transform::from_translation(Vec3::new(position.x(), position.y(), 20.0)),
+            sprite: Sprite::new(Vec2::new(width, 30.0)),
+            ..Default::default()
+        })
+        .with(Paddle)
+        .with(Collider)
+        .with(Bounce::Steer)
+        .with(ResetsCombo)
+        .with(ColorTransfer::FromBall)
+        .with(DespawnOnEnd)
+        .with(Name("Paddle".into()));
+}
+
+fn paddle_movement_system(
+    time: Res<Time>,
+    tuning: Res<Tuning>,
+    arena: Res<Arena>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut key_combos_resource: Local<Option<HashMap<Handlers, KeyCombo>>>,
+    mut query: Query<(&Paddle, &mut Transform)>,
+) {
+    // initialise local
+    if let None = *key_combos_resource {
+        let mut h: HashMap<Handlers, KeyCombo> = HashMap::new();
+        h.insert(
+            Handlers::DoubleTapLeft,
+            KeyCombo::new(
+                vec![Keypress::new(KeyCode::Left), Keypress::new(KeyCode::Left)],
+                0.5,
+                0.25,
+                false,
+            ),
+        );
+        h.insert(
+            Handlers::DoubleTapRight,
+            KeyCombo::new(
+                vec![Keypress::new(KeyCode::Right), Keypress::new(KeyCode::Right)],
+                0.5,
+                0.25,
+                false,
+            ),
+        );
+        *key_combos_resource = Some(h);
+    }
+    for (_paddle, mut transform) in &mut query.iter() {
+        let mut direction = 0.0;
+        if keyboard_input.pressed(KeyCode::Left) {
+            direction -= 1.0;
+        }
+        if keyboard_input.pressed(KeyCode::Right) {
+            direction += 1.0;
+        }
+        // if both are pressed at the same time, we don't move, i.e. direction = 0.0
+        if let Some(key_combos) = &mut *key_combos_resource {
+            if let Some(handler) = key_combos.get_mut(&Handlers::DoubleTapLeft) {
+                if keyboard_input.pressed(KeyCode::Right) {
+                    handler.reset();
+                } else if handler.done(&keyboard_input, time.delta_seconds) {
+                    // temporary, instead increase the paddle speed temporarily
+                    *transform.translation.x_mut() -= tuning.dash_distance;
+                }
+            }
+            if let Some(handler) = key_combos.get_mut(&Handlers::DoubleTapRight) {
+                if keyboard_input.pressed(KeyCode::Left) {
+                    handler.reset();
+                } else if handler.done(&keyboard_input, time.delta_seconds) {
+                    // temporary, instead increase the paddle speed temporarily
+                    *transform.translation.x_mut() += tuning.dash_distance;
+                }
+            }
+        }
+
+        *transform.translation.x_mut() += time.delta_seconds * direction * tuning.paddle_speed;
+
+        // bound the paddle partially within the walls, its middle at most 50 past a side wall
+        let max_x = arena.width / 2.0 + 50.0;
+        *transform.translation.x_mut() = transform.translation.x().max(-max_x).min(max_x);
+    }
+}
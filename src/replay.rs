@@ -0,0 +1,362 @@
+//! Input recording and deterministic playback.
+//!
+//! A replay file is line based: the first line is a [`ReplayHeader`], and every line after that
+//! is a [`ReplayTick`], both written as RON. Each tick is flushed as soon as it is recorded, so a
+//! run that ends in a crash (or a ball escaping the arena) still leaves a usable file behind.
+//!
+//! During playback the recorded keys are written back into `Input<KeyCode>` and the recorded
+//! frame time into `Time`, so `paddle_movement_system`, `start_pause_game_system` and friends run
+//! exactly as they did during the recording. Menu choices are recorded as [`MenuAction`]s and
+//! sent again, rather than replaying the keyboard and mouse that made them. A saved game that is
+//! continued is recorded along with the "Continue", as the save file is gone once it's loaded.
+//! Likewise the header records the [`RunSetup`] the run started from, and the tuning file is
+//! recorded again whenever it is reloaded.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bricks::Levels,
+    difficulty::Difficulty,
+    highscore::NameEntry,
+    menu::MenuAction,
+    save::{SaveGame, SaveSlot},
+    state::{GameState, StateMachine},
+    tuning::{CustomTuning, Tuning},
+};
+
+/// Bump this whenever [`ReplayHeader`] or [`ReplayTick`] change shape
+const REPLAY_VERSION: u32 = 7;
+
+/// Records or plays back the run, depending on the [`Replay`] resource, which has to be added
+/// before this plugin
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // playback overwrites this frame's input and time before anything reads them
+        app.add_system_to_stage(stage::PRE_UPDATE, replay_playback_system.system())
+            // recording happens last, once this frame's menu actions have been sent
+            .add_system_to_stage(stage::LAST, replay_record_system.system());
+    }
+}
+
+/// Where the current run is recorded to if `--record` isn't given
+pub const DEFAULT_REPLAY_PATH: &str = "last_run.replay";
+
+/// The keys that affect gameplay, and so are the only ones worth recording
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ReplayKey {
+    Left,
+    Right,
+    Space,
+    R,
+}
+
+impl ReplayKey {
+    const ALL: [ReplayKey; 4] = [
+        ReplayKey::Left,
+        ReplayKey::Right,
+        ReplayKey::Space,
+        ReplayKey::R,
+    ];
+
+    fn key_code(self) -> KeyCode {
+        match self {
+            ReplayKey::Left => KeyCode::Left,
+            ReplayKey::Right => KeyCode::Right,
+            ReplayKey::Space => KeyCode::Space,
+            ReplayKey::R => KeyCode::R,
+        }
+    }
+}
+
+/// What a run starts from, which has to be the same for its playback to play out the same
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSetup {
+    /// The seed of the run's `GameRng`
+    pub seed: u64,
+    /// The tuning file, before the difficulty is applied
+    pub tuning: Tuning,
+    pub difficulty: Difficulty,
+    pub levels: Levels,
+    /// Index into `levels` of the level the run starts on
+    pub level: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    pub version: u32,
+    pub setup: RunSetup,
+}
+
+/// Everything the game systems read from the outside world during one frame
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReplayTick {
+    pub delta_seconds: f32,
+    pub pressed: Vec<ReplayKey>,
+    pub just_pressed: Vec<ReplayKey>,
+    pub just_released: Vec<ReplayKey>,
+    pub menu_actions: Vec<MenuAction>,
+    /// The saved game loaded by a [`MenuAction::Continue`] this frame
+    pub loaded_game: Option<SaveGame>,
+    /// The tuning file reloaded this frame
+    pub tuning: Option<Tuning>,
+}
+
+impl ReplayTick {
+    fn capture(delta_seconds: f32, input: &Input<KeyCode>) -> Self {
+        let mut tick = ReplayTick {
+            delta_seconds,
+            ..Default::default()
+        };
+        for &key in ReplayKey::ALL.iter() {
+            if input.pressed(key.key_code()) {
+                tick.pressed.push(key);
+            }
+            if input.just_pressed(key.key_code()) {
+                tick.just_pressed.push(key);
+            }
+            if input.just_released(key.key_code()) {
+                tick.just_released.push(key);
+            }
+        }
+        tick
+    }
+
+    /// Rebuild an `Input<KeyCode>` that answers `pressed`, `just_pressed` and `just_released`
+    /// exactly as the recorded one did. `Input` can only be driven through `press`, `release` and
+    /// `update`, hence the dance.
+    fn restore(&self, input: &mut Input<KeyCode>) {
+        *input = Input::default();
+        // keys that were already down (or released this frame) were pressed in an earlier frame
+        for key in self.pressed.iter().chain(self.just_released.iter()) {
+            if !self.just_pressed.contains(key) {
+                input.press(key.key_code());
+            }
+        }
+        input.update();
+        for key in self.just_released.iter() {
+            input.release(key.key_code());
+        }
+        for key in self.just_pressed.iter() {
+            input.press(key.key_code());
+            // pressed and released within the same frame
+            if !self.pressed.contains(key) {
+                input.release(key.key_code());
+            }
+        }
+    }
+}
+
+pub struct ReplayRecorder {
+    writer: BufWriter<File>,
+    /// The tuning file as last recorded, to spot reloads
+    tuning: Tuning,
+}
+
+impl ReplayRecorder {
+    pub fn create(path: &Path, setup: &RunSetup) -> io::Result<Self> {
+        let mut recorder = Self {
+            writer: BufWriter::new(File::create(path)?),
+            tuning: setup.tuning.clone(),
+        };
+        recorder.write_line(&ReplayHeader {
+            version: REPLAY_VERSION,
+            setup: setup.clone(),
+        })?;
+        Ok(recorder)
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) -> io::Result<()> {
+        let line =
+            ron::ser::to_string(value).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()
+    }
+}
+
+pub struct ReplayPlayer {
+    ticks: Vec<ReplayTick>,
+    index: usize,
+    paused: bool,
+    fast_forward: bool,
+    /// Quit the game once the last tick has played, rather than pausing on it
+    exit_when_finished: bool,
+    /// The state machine as it was before playback was paused, put back as is on resuming
+    paused_state: Option<StateMachine>,
+}
+
+impl ReplayPlayer {
+    pub fn load(path: &Path) -> io::Result<(ReplayHeader, Self)> {
+        let invalid = |e: ron::Error| io::Error::new(io::ErrorKind::InvalidData, e);
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header: ReplayHeader = match lines.next() {
+            Some(line) => ron::de::from_str(&line?).map_err(invalid)?,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "empty replay file",
+                ))
+            }
+        };
+        if header.version != REPLAY_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "replay version {} is not supported (expected {})",
+                    header.version, REPLAY_VERSION
+                ),
+            ));
+        }
+        let mut ticks = Vec::new();
+        for line in lines {
+            let line = line?;
+            match ron::de::from_str(&line) {
+                Ok(tick) => ticks.push(tick),
+                // the last line may be cut short if the recording crashed mid-write
+                Err(_) => break,
+            }
+        }
+        Ok((
+            header,
+            Self {
+                ticks,
+                index: 0,
+                paused: false,
+                fast_forward: false,
+                exit_when_finished: false,
+                paused_state: None,
+            },
+        ))
+    }
+}
+
+/// Whether the current run is being recorded, played back, or neither
+pub enum Replay {
+    Off,
+    Recording(ReplayRecorder),
+    Playback(ReplayPlayer),
+}
+
+impl Replay {
+    /// Record the run, which starts from `setup`, to `path`
+    pub fn record(path: &Path, setup: &RunSetup) -> io::Result<Self> {
+        Ok(Replay::Recording(ReplayRecorder::create(path, setup)?))
+    }
+
+    /// Play back the replay at `path`, returning the setup the run has to start from. With
+    /// `exit_when_finished` the game quits at the end of the replay, for running it headless.
+    pub fn play(path: &Path, exit_when_finished: bool) -> io::Result<(Self, RunSetup)> {
+        let (header, mut player) = ReplayPlayer::load(path)?;
+        player.exit_when_finished = exit_when_finished;
+        Ok((Replay::Playback(player), header.setup))
+    }
+}
+
+/// Appends this frame's time, gameplay keys, menu actions and any tuning reload to the replay file
+fn replay_record_system(
+    mut replay: ResMut<Replay>,
+    mut menu_action_reader: Local<EventReader<MenuAction>>,
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    menu_actions: Res<Events<MenuAction>>,
+    name_entry: Res<NameEntry>,
+    save_slot: Res<SaveSlot>,
+    custom_tuning: Res<CustomTuning>,
+) {
+    if let Replay::Recording(recorder) = &mut *replay {
+        // a replay never asks for a name, so keys typed into one would be taken as gameplay
+        let mut tick = if name_entry.active() {
+            ReplayTick {
+                delta_seconds: time.delta_seconds,
+                ..Default::default()
+            }
+        } else {
+            ReplayTick::capture(time.delta_seconds, &keyboard_input)
+        };
+        tick.menu_actions = menu_action_reader.iter(&menu_actions).copied().collect();
+        if tick.menu_actions.contains(&MenuAction::Continue) {
+            tick.loaded_game = save_slot.pending.clone();
+        }
+        if custom_tuning.0 != recorder.tuning {
+            recorder.tuning = custom_tuning.0.clone();
+            tick.tuning = Some(custom_tuning.0.clone());
+        }
+        if let Err(e) = recorder.write_line(&tick) {
+            println!("failed to record replay, recording stopped: {}", e);
+            *replay = Replay::Off;
+        }
+    }
+}
+
+/// Feeds the recorded ticks back into `Time`, `Input<KeyCode>`, the menu and the tuning.
+///
+/// The real keyboard still controls the playback itself: `Space` pauses and resumes, and `F`
+/// toggles fast-forward (by turning off vsync, so frames run as fast as they can).
+fn replay_playback_system(
+    mut replay: ResMut<Replay>,
+    mut state: ResMut<StateMachine>,
+    mut time: ResMut<Time>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut menu_actions: ResMut<Events<MenuAction>>,
+    mut save_slot: ResMut<SaveSlot>,
+    mut custom_tuning: ResMut<CustomTuning>,
+    mut windows: ResMut<Windows>,
+    mut app_exit_events: ResMut<Events<AppExit>>,
+) {
+    if let Replay::Playback(player) = &mut *replay {
+        if keyboard_input.just_released(KeyCode::F) {
+            player.fast_forward = !player.fast_forward;
+            if let Some(window) = windows.get_primary_mut() {
+                window.set_vsync(!player.fast_forward);
+            }
+        }
+        let finished = player.index >= player.ticks.len();
+        if finished && player.exit_when_finished {
+            app_exit_events.send(AppExit);
+            return;
+        }
+        if keyboard_input.just_released(KeyCode::Space) || (finished && !player.paused) {
+            player.paused = !player.paused;
+            if player.paused {
+                // swapped out wholesale rather than transitioned, so no hooks run and any pending
+                // transition survives the pause
+                player.paused_state = Some(std::mem::replace(
+                    &mut *state,
+                    StateMachine::new(GameState::Paused),
+                ));
+            } else if let Some(paused_state) = player.paused_state.take() {
+                *state = paused_state;
+            }
+        }
+
+        if player.paused || finished {
+            *keyboard_input = Input::default();
+            return;
+        }
+
+        let tick = &player.ticks[player.index];
+        player.index += 1;
+        tick.restore(&mut keyboard_input);
+        for action in tick.menu_actions.iter() {
+            menu_actions.send(*action);
+        }
+        if let Some(game) = &tick.loaded_game {
+            save_slot.pending = Some(game.clone());
+        }
+        if let Some(recorded) = &tick.tuning {
+            custom_tuning.0 = recorded.clone();
+        }
+        time.delta_seconds = tick.delta_seconds;
+        time.delta_seconds_f64 = tick.delta_seconds as f64;
+        time.delta = Duration::from_secs_f32(tick.delta_seconds);
+    }
+}
@@ -0,0 +1,351 @@
+//! Saving a game part way through a level, and picking it up again later.
+//!
+//! "Save and quit" in the pause menu writes the paddle, the balls, the bricks still standing and
+//! the score to `save.ron` in the user's data directory, and "Continue" on the title screen puts
+//! them all back, along with the lives left and the difficulty. The save is used up by continuing
+//! it, so a level can't be replayed from the same point over and over. There are no power-ups yet,
+//! so there is nothing to save for them.
+//!
+//! A replay doesn't read the save file: the game that was loaded is recorded in the replay
+//! itself, so that the replay still works once the save is gone.
+
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::Arena,
+    ball::{spawn_ball, Ball, Spin},
+    bricks::{spawn_brick, Brick, BrickType, CurrentLevel, Levels},
+    data_file,
+    difficulty::{Difficulty, GameDifficulty},
+    game::{Lives, Name},
+    materials::MaterialPool,
+    menu::MenuAction,
+    paddle::{spawn_paddle, Paddle},
+    replay::Replay,
+    scoring::{Combo, LevelTimer, Scoreboard},
+    state::{GameState, StateMachine},
+    write_data_file,
+};
+
+/// Bump this whenever [`SaveGame`] changes shape
+const SAVE_VERSION: u32 = 3;
+
+/// Adds the "Save and quit" and "Continue" menu items. It has to be added after the `GamePlugin`,
+/// as it replaces the `GamePlugin`'s empty [`SaveSlot`].
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(SaveSlot::new())
+            .add_system(save_menu_system.system())
+            .add_system(continue_menu_system.system());
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedPaddle {
+    x: f32,
+    width: f32,
+    color: [f32; 4],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedBall {
+    translation: [f32; 3],
+    velocity: [f32; 3],
+    rotation: f32,
+    rotational_velocity: f32,
+    spin: Spin,
+    last_paddle_offset: f32,
+    color: [f32; 4],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedBrick {
+    name: String,
+    translation: [f32; 3],
+    size: [f32; 2],
+    brick_type: BrickType,
+    color: [f32; 4],
+}
+
+/// Everything needed to carry on with a level from where it was left
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveGame {
+    version: u32,
+    /// Index into [`Levels`]
+    level: usize,
+    score: usize,
+    combo_hits: usize,
+    elapsed: f32,
+    lives: usize,
+    difficulty: Difficulty,
+    paddle: SavedPaddle,
+    balls: Vec<SavedBall>,
+    /// Only the bricks still standing, broken ones are fading out and aren't worth keeping
+    bricks: Vec<SavedBrick>,
+}
+
+impl SaveGame {
+    /// The difficulty the game was played at, which it carries on at when it's continued
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+}
+
+/// The default slot has nowhere to save to, for games that don't save, like tests
+#[derive(Default)]
+pub struct SaveSlot {
+    /// Where the game is saved, or `None` if there's nowhere to save it
+    path: Option<PathBuf>,
+    exists: bool,
+    /// A game that was just loaded, waiting for [`GameState::Starting`] to spawn it
+    pub pending: Option<SaveGame>,
+}
+
+impl SaveSlot {
+    pub fn new() -> Self {
+        let path = data_file("save.ron");
+        let exists = path.as_ref().map_or(false, |path| path.exists());
+        Self {
+            path,
+            exists,
+            pending: None,
+        }
+    }
+
+    /// Whether there is a saved game to continue
+    pub fn exists(&self) -> bool {
+        self.exists
+    }
+
+    fn save(&mut self, game: &SaveGame) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+        let result = ron::ser::to_string_pretty(game, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+            .and_then(|contents| write_data_file(path, &contents).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => self.exists = true,
+            Err(e) => println!("could not save the game to {}: {}", path.display(), e),
+        }
+    }
+
+    /// Load the saved game and remove it, so that it can only be continued once. A save for a
+    /// level past the last of `level_count` levels is thrown away.
+    fn take(&mut self, level_count: usize) -> Option<SaveGame> {
+        let path = self.path.as_ref()?;
+        self.exists = false;
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                println!("could not read {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        let _ = fs::remove_file(path);
+        match ron::de::from_str::<SaveGame>(&contents) {
+            Ok(game) if game.version != SAVE_VERSION => {
+                println!(
+                    "{} is from a different version of the game (save version {}), ignoring it",
+                    path.display(),
+                    game.version
+                );
+                None
+            }
+            Ok(game) if game.level >= level_count => {
+                println!("{} is for a level that doesn't exist", path.display());
+                None
+            }
+            Ok(game) => Some(game),
+            Err(e) => {
+                println!("could not read {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+fn color_of(materials: &Assets<ColorMaterial>, handle: &Handle<ColorMaterial>) -> [f32; 4] {
+    let color = materials
+        .get(handle)
+        .map_or(Color::WHITE, |material| material.color);
+    [color.r, color.g, color.b, color.a]
+}
+
+fn to_color([r, g, b, a]: [f32; 4]) -> Color {
+    Color::rgba(r, g, b, a)
+}
+
+fn to_array(vec: Vec3) -> [f32; 3] {
+    [vec.x(), vec.y(), vec.z()]
+}
+
+/// Handles "Save and quit" from the pause menu
+fn save_menu_system(
+    mut action_reader: Local<EventReader<MenuAction>>,
+    actions: Res<Events<MenuAction>>,
+    replay: Res<Replay>,
+    materials: Res<Assets<ColorMaterial>>,
+    scoreboard: Res<Scoreboard>,
+    level_timer: Res<LevelTimer>,
+    combo: Res<Combo>,
+    lives: Res<Lives>,
+    current_level: Res<CurrentLevel>,
+    game_difficulty: Res<GameDifficulty>,
+    mut state: ResMut<StateMachine>,
+    mut save_slot: ResMut<SaveSlot>,
+    mut paddle_query: Query<(&Paddle, &Transform, &Sprite, &Handle<ColorMaterial>)>,
+    mut ball_query: Query<(&Ball, &Transform, &Handle<ColorMaterial>)>,
+    mut brick_query: Query<(
+        &Brick,
+        &BrickType,
+        &Name,
+        &Transform,
+        &Sprite,
+        &Handle<ColorMaterial>,
+    )>,
+) {
+    let playback = matches!(*replay, Replay::Playback(_));
+    for action in action_reader.iter(&actions) {
+        if *action == MenuAction::SaveAndQuit {
+            // a replay leaves the player's own save alone
+            if !playback {
+                let mut paddle = SavedPaddle {
+                    x: 0.0,
+                    width: 120.0,
+                    color: [0.0, 0.0, 0.0, 1.0],
+                };
+                for (_paddle, transform, sprite, material) in &mut paddle_query.iter() {
+                    paddle = SavedPaddle {
+                        x: transform.translation.x(),
+                        width: sprite.size.x(),
+                        color: color_of(&materials, material),
+                    };
+                }
+                let mut balls = Vec::new();
+                for (ball, transform, material) in &mut ball_query.iter() {
+                    balls.push(SavedBall {
+                        translation: to_array(transform.translation),
+                        velocity: to_array(ball.velocity),
+                        // the spin turns the transform, `Ball::rotation` is where it started
+                        rotation: transform.rotation.to_axis_angle().1,
+                        rotational_velocity: ball.rotational_velocity,
+                        spin: ball.spin,
+                        last_paddle_offset: ball.last_paddle_offset,
+                        color: color_of(&materials, material),
+                    });
+                }
+                let mut bricks = Vec::new();
+                for (brick, brick_type, name, transform, sprite, material) in
+                    &mut brick_query.iter()
+                {
+                    if brick.0 {
+                        bricks.push(SavedBrick {
+                            name: name.0.clone(),
+                            translation: to_array(transform.translation),
+                            size: [sprite.size.x(), sprite.size.y()],
+                            brick_type: *brick_type,
+                            color: color_of(&materials, material),
+                        });
+                    }
+                }
+                save_slot.save(&SaveGame {
+                    version: SAVE_VERSION,
+                    level: current_level.0,
+                    score: scoreboard.score,
+                    combo_hits: combo.hits,
+                    elapsed: level_timer.elapsed,
+                    lives: lives.remaining,
+                    difficulty: game_difficulty.0,
+                    paddle,
+                    balls,
+                    bricks,
+                });
+            }
+            state.set(GameState::Title);
+        }
+    }
+}
+
+/// Handles "Continue" from the title screen
+fn continue_menu_system(
+    mut action_reader: Local<EventReader<MenuAction>>,
+    actions: Res<Events<MenuAction>>,
+    replay: Res<Replay>,
+    levels: Res<Levels>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut state: ResMut<StateMachine>,
+    mut save_slot: ResMut<SaveSlot>,
+) {
+    let playback = matches!(*replay, Replay::Playback(_));
+    for action in action_reader.iter(&actions) {
+        if *action == MenuAction::Continue {
+            // during playback the recorded game has already been put in the slot
+            if !playback {
+                save_slot.pending = save_slot.take(levels.0.len());
+            }
+            if let Some(game) = &save_slot.pending {
+                current_level.0 = game.level;
+            }
+            state.set(GameState::Restarting);
+        }
+    }
+}
+
+/// Spawns a saved game, in place of the fresh level `start_game_system` would have spawned
+pub fn load_game(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    pool: &mut MaterialPool,
+    arena: &Arena,
+    game: SaveGame,
+    scoreboard: &mut Scoreboard,
+    level_timer: &mut LevelTimer,
+    combo: &mut Combo,
+    lives: &mut Lives,
+) {
+    spawn_paddle(
+        commands,
+        materials,
+        Vec2::new(game.paddle.x, arena.paddle_y()),
+        game.paddle.width,
+        to_color(game.paddle.color),
+    );
+    for ball in game.balls {
+        spawn_ball(
+            commands,
+            materials,
+            ball.translation.into(),
+            Ball {
+                velocity: ball.velocity.into(),
+                rotation: ball.rotation,
+                rotational_velocity: ball.rotational_velocity,
+                spin: ball.spin,
+                last_paddle_offset: ball.last_paddle_offset,
+            },
+            to_color(ball.color),
+        );
+    }
+    for brick in game.bricks {
+        spawn_brick(
+            commands,
+            materials,
+            pool,
+            brick.translation.into(),
+            brick.size.into(),
+            to_color(brick.color),
+            brick.brick_type,
+            brick.name,
+        );
+    }
+    scoreboard.score = game.score;
+    level_timer.elapsed = game.elapsed;
+    combo.hits = game.combo_hits;
+    lives.remaining = game.lives;
+}
@@ -0,0 +1,50 @@
+//! The stages the game's systems run in each frame, in order:
+//!
+//! 1. Bevy's `FIRST` and `EVENT_UPDATE`, which advance the clock and read the keyboard and mouse
+//! 2. [`STATE_TRANSITION`] and [`STATE_HOOKS`], which apply the transition asked for last frame
+//! 3. Bevy's `PRE_UPDATE`, where a replay feeds in its recorded input
+//! 4. [`INPUT`]: turning keys and clicks into actions, like pausing or choosing a menu item
+//! 5. [`COLLISION`]: finding what the balls will hit this frame
+//! 6. [`MOVEMENT`]: moving and bouncing the balls, and moving the paddle
+//! 7. Bevy's `UPDATE`: the rules of the game, menus and timers
+//! 8. [`EFFECTS`]: what happens because of a collision, like breaking bricks and scoring
+//! 9. [`CLEANUP`]: fading out, and asking for what has faded out to be despawned
+//! 10. [`UI`]: updating the HUD and menus to show the frame's results
+//! 11. [`DEBUG_DRAW`]: drawing the debug lines asked for this frame, see [`crate::debug_lines`]
+//! 12. [`DESPAWN`]: despawning everything asked for this frame, see [`crate::despawn`]
+//! 13. Bevy's `POST_UPDATE` and `LAST`, which lay out and draw, and record the replay
+//!
+//! Commands are applied at the end of each stage, so an entity spawned in one stage is there for
+//! every later stage. Despawning waits for [`DESPAWN`], so an entity asked to be despawned is
+//! still there for the rest of the frame. Collision events are sent in [`COLLISION`] and can
+//! be read by any later stage in the same frame, and the collider that was hit is still there for
+//! them to look at until the end of [`EFFECTS`].
+//!
+//! Within a stage, systems that access the same resources or components run in the order they
+//! were added, and others may run at the same time.
+//!
+//! [`STATE_TRANSITION`]: crate::state::STATE_TRANSITION
+//! [`STATE_HOOKS`]: crate::state::STATE_HOOKS
+
+use bevy::prelude::*;
+
+pub const INPUT: &str = "input";
+pub const COLLISION: &str = "collision";
+pub const MOVEMENT: &str = "movement";
+pub const EFFECTS: &str = "effects";
+pub const CLEANUP: &str = "cleanup";
+pub const UI: &str = "ui";
+pub const DEBUG_DRAW: &str = "debug_draw";
+pub const DESPAWN: &str = "despawn";
+
+/// Adds the stages above around Bevy's `UPDATE`
+pub fn add_stages(app: &mut AppBuilder) {
+    app.add_stage_after(stage::PRE_UPDATE, INPUT)
+        .add_stage_after(INPUT, COLLISION)
+        .add_stage_after(COLLISION, MOVEMENT)
+        .add_stage_after(stage::UPDATE, EFFECTS)
+        .add_stage_after(EFFECTS, CLEANUP)
+        .add_stage_after(CLEANUP, UI)
+        .add_stage_after(UI, DEBUG_DRAW)
+        .add_stage_after(DEBUG_DRAW, DESPAWN);
+}
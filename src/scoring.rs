@@ -0,0 +1,112 @@
+//! The score: points per brick with a combo multiplier, the level timer and the time bonus.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::PLAYING,
+    state::{on_enter, run_in, GameState, STATE_HOOKS},
+    tuning::Tuning,
+};
+
+pub struct ScoringPlugin;
+
+impl Plugin for ScoringPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(Scoreboard { score: 0 })
+            .add_resource(LevelTimer { elapsed: 0.0 })
+            .add_resource(Combo { hits: 0 })
+            .add_event::<BrickScored>()
+            .add_resource(LevelResult {
+                brick_score: 0,
+                seconds: 0.0,
+                time_bonus: 0,
+            })
+            .add_system_to_stage(
+                STATE_HOOKS,
+                on_enter(GameState::Win, level_complete_system.system()),
+            )
+            .add_system(run_in(PLAYING, level_timer_system.system()));
+    }
+}
+
+pub struct Scoreboard {
+    pub score: usize,
+}
+
+/// How long the current level has been played for, not counting pauses
+pub struct LevelTimer {
+    pub elapsed: f32,
+}
+
+/// How the completion bonus falls off with the time taken to finish a level
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BonusCurve {
+    /// Falls in a straight line to nothing at `zero_at` seconds
+    Linear { zero_at: f32 },
+    /// Halves every `half_life` seconds
+    Exponential { half_life: f32 },
+}
+
+/// The score bonus for finishing a level quickly
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeBonus {
+    /// The bonus for finishing instantly
+    pub max: usize,
+    pub curve: BonusCurve,
+}
+
+impl TimeBonus {
+    pub fn bonus(&self, seconds: f32) -> usize {
+        let fraction = match self.curve {
+            BonusCurve::Linear { zero_at } => (1.0 - seconds / zero_at).max(0.0),
+            BonusCurve::Exponential { half_life } => 0.5f32.powf(seconds / half_life),
+        };
+        (self.max as f32 * fraction).round() as usize
+    }
+}
+
+/// The score breakdown of the last completed level
+pub struct LevelResult {
+    pub brick_score: usize,
+    pub seconds: f32,
+    pub time_bonus: usize,
+}
+
+/// Consecutive brick hits since the ball last touched the paddle
+pub struct Combo {
+    pub hits: usize,
+}
+
+impl Combo {
+    /// At least 1, even if the cap is set to 0
+    pub fn multiplier(&self, max_multiplier: usize) -> usize {
+        self.hits.max(1).min(max_multiplier.max(1))
+    }
+}
+
+/// Sent when a brick is broken, with the points it scored and where it was
+pub struct BrickScored {
+    pub position: Vec3,
+    pub points: usize,
+}
+
+fn level_timer_system(time: Res<Time>, mut level_timer: ResMut<LevelTimer>) {
+    level_timer.elapsed += time.delta_seconds;
+}
+
+/// Runs on entering [`GameState::Win`], adding the time bonus to the score
+fn level_complete_system(
+    level_timer: Res<LevelTimer>,
+    tuning: Res<Tuning>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut level_result: ResMut<LevelResult>,
+) {
+    let bonus = tuning.time_bonus.bonus(level_timer.elapsed);
+    *level_result = LevelResult {
+        brick_score: scoreboard.score,
+        seconds: level_timer.elapsed,
+        time_bonus: bonus,
+    };
+    scoreboard.score += bonus;
+}
@@ -0,0 +1,199 @@
+//! The game's state machine.
+//!
+//! Systems ask for a transition with [`StateMachine::set`], which is checked against the allowed
+//! transitions and applied by [`state_transition_system`] at the start of the next frame. Systems
+//! that should only run in some states are wrapped with [`run_in`], and systems that should run
+//! once when a state is entered or left are wrapped with [`on_enter`] or [`on_exit`] and added to
+//! the [`STATE_HOOKS`] stage.
+
+use std::{any::TypeId, borrow::Cow};
+
+use bevy::{
+    ecs::{ArchetypeAccess, SystemId, ThreadLocalExecution, TypeAccess},
+    prelude::*,
+};
+
+/// Applies the transition requested during the previous frame
+pub const STATE_TRANSITION: &str = "state_transition";
+/// Runs the [`on_enter`] and [`on_exit`] systems for the transition that was just applied
+pub const STATE_HOOKS: &str = "state_hooks";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameState {
+    Title,
+    Starting,
+    Restarting,
+    /// Counting down to [`GameState::Playing`], the paddle can move but the ball can't
+    Countdown,
+    Playing,
+    Paused,
+    Win,
+    Lose,
+}
+
+impl GameState {
+    fn can_become(self, next: GameState) -> bool {
+        use GameState::*;
+        match (self, next) {
+            (Title, Restarting) => true,
+            (Restarting, Starting) => true,
+            (Starting, Countdown) | (Starting, Restarting) => true,
+            (Countdown, Playing) | (Countdown, Paused) | (Countdown, Restarting) => true,
+            (Playing, Paused) | (Playing, Win) | (Playing, Lose) | (Playing, Restarting) => true,
+            // a new ball served after losing one
+            (Playing, Countdown) => true,
+            (Paused, Countdown) | (Paused, Restarting) | (Paused, Title) => true,
+            (Win, Restarting) | (Win, Title) => true,
+            (Lose, Restarting) | (Lose, Title) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StateMachine {
+    current: GameState,
+    next: Option<GameState>,
+    /// The transition applied this frame, if any, as `(from, to)`
+    transition: Option<(GameState, GameState)>,
+}
+
+impl StateMachine {
+    pub fn new(state: GameState) -> Self {
+        Self {
+            current: state,
+            next: None,
+            transition: None,
+        }
+    }
+
+    pub fn current(&self) -> GameState {
+        self.current
+    }
+
+    /// Request a transition, which happens at the start of the next frame. Transitions that
+    /// aren't allowed are logged and ignored, and asking for the current state does nothing.
+    pub fn set(&mut self, next: GameState) {
+        let from = self.next.unwrap_or(self.current);
+        if from == next {
+            return;
+        }
+        if from.can_become(next) {
+            self.next = Some(next);
+        } else {
+            println!(
+                "ignoring invalid game state transition from {:?} to {:?}",
+                from, next
+            );
+        }
+    }
+
+    pub fn entered(&self, state: GameState) -> bool {
+        matches!(self.transition, Some((_, to)) if to == state)
+    }
+
+    pub fn exited(&self, state: GameState) -> bool {
+        matches!(self.transition, Some((from, _)) if from == state)
+    }
+}
+
+pub fn state_transition_system(mut state: ResMut<StateMachine>) {
+    state.transition = None;
+    if let Some(next) = state.next.take() {
+        state.transition = Some((state.current, next));
+        state.current = next;
+    }
+}
+
+enum RunCriteria {
+    In(&'static [GameState]),
+    Enter(GameState),
+    Exit(GameState),
+}
+
+/// Wraps a system so that it only runs when its [`RunCriteria`] are met
+struct StateSystem {
+    criteria: RunCriteria,
+    system: Box<dyn System>,
+    resource_access: TypeAccess,
+}
+
+impl StateSystem {
+    fn new(criteria: RunCriteria, system: Box<dyn System>) -> Box<dyn System> {
+        // the wrapper reads the state machine on top of whatever the system itself uses
+        let mut resource_access = system.resource_access().clone();
+        let state_type = TypeId::of::<StateMachine>();
+        if !resource_access.mutable.contains(&state_type) {
+            resource_access.immutable.insert(state_type);
+        }
+        Box::new(Self {
+            criteria,
+            system,
+            resource_access,
+        })
+    }
+}
+
+impl System for StateSystem {
+    fn name(&self) -> Cow<'static, str> {
+        self.system.name()
+    }
+
+    fn id(&self) -> SystemId {
+        self.system.id()
+    }
+
+    fn update_archetype_access(&mut self, world: &World) {
+        self.system.update_archetype_access(world);
+    }
+
+    fn archetype_access(&self) -> &ArchetypeAccess {
+        self.system.archetype_access()
+    }
+
+    fn resource_access(&self) -> &TypeAccess {
+        &self.resource_access
+    }
+
+    fn thread_local_execution(&self) -> ThreadLocalExecution {
+        self.system.thread_local_execution()
+    }
+
+    fn run(&mut self, world: &World, resources: &Resources) {
+        let should_run = {
+            let state = resources.get::<StateMachine>().unwrap();
+            match self.criteria {
+                RunCriteria::In(states) => states.contains(&state.current),
+                RunCriteria::Enter(to) => state.entered(to),
+                RunCriteria::Exit(from) => state.exited(from),
+            }
+        };
+        if should_run {
+            self.system.run(world, resources);
+        }
+    }
+
+    fn run_thread_local(&mut self, world: &mut World, resources: &mut Resources) {
+        // always let the system flush, it does nothing if the system didn't run
+        self.system.run_thread_local(world, resources);
+    }
+
+    fn initialize(&mut self, resources: &mut Resources) {
+        self.system.initialize(resources);
+    }
+}
+
+/// Only run `system` while the game is in one of `states`
+pub fn run_in(states: &'static [GameState], system: Box<dyn System>) -> Box<dyn System> {
+    StateSystem::new(RunCriteria::In(states), system)
+}
+
+/// Run `system` once, in the frame `state` is entered
+pub fn on_enter(state: GameState, system: Box<dyn System>) -> Box<dyn System> {
+    StateSystem::new(RunCriteria::Enter(state), system)
+}
+
+/// Run `system` once, in the frame `state` is left
+pub fn on_exit(state: GameState, system: Box<dyn System>) -> Box<dyn System> {
+    StateSystem::new(RunCriteria::Exit(state), system)
+}
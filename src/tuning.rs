@@ -0,0 +1,186 @@
+//! The numbers that decide how the game plays, read from `assets/tuning.ron` and reloaded
+//! whenever that file changes, so that the game can be balanced while it runs.
+//!
+//! Anything left out of the file keeps its default, and a file that can't be read is reported and
+//! otherwise ignored. The file's numbers for the ball, the paddle and the lives only count on the
+//! Custom [`Difficulty`], the other difficulties set their own. A replay records the tuning it was
+//! played with, and plays back with that rather than with the file.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    difficulty::{Difficulty, GameDifficulty},
+    replay::Replay,
+    scoring::{BonusCurve, TimeBonus},
+};
+
+pub const TUNING_PATH: &str = "assets/tuning.ron";
+
+/// How often the file is checked for changes, in seconds
+const CHECK_INTERVAL: f32 = 0.5;
+
+/// Replaces the `GamePlugin`'s default [`Tuning`] with `tuning` at `difficulty`, and reloads it
+/// from [`TUNING_PATH`] whenever the file changes. It has to be added after the `GamePlugin`, and
+/// needs the [`Replay`] resource.
+pub struct TuningPlugin {
+    pub tuning: Tuning,
+    pub difficulty: Difficulty,
+}
+
+impl Plugin for TuningPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(self.difficulty.apply(&self.tuning))
+            .add_resource(CustomTuning(self.tuning.clone()))
+            .add_resource(self.difficulty)
+            .add_resource(GameDifficulty(self.difficulty))
+            .add_resource(TuningFile::new(TUNING_PATH.into()))
+            .add_system_to_stage(stage::PRE_UPDATE, tuning_reload_system.system());
+    }
+}
+
+/// The numbers the game plays with, which are the [`CustomTuning`] with the [`Difficulty`]
+/// applied
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tuning {
+    /// The ball's speed when a level starts
+    pub ball_speed: f32,
+    /// How much faster the ball gets with each brick it hits
+    pub brick_speed_up: f32,
+    /// How much slower the ball gets with each wall it hits
+    pub wall_slow_down: f32,
+    /// However many walls the ball hits, it never slows down below this
+    pub min_ball_speed: f32,
+    /// How far from straight up, in degrees, the ball leaves the very edge of the paddle
+    pub max_paddle_angle: f32,
+    pub paddle_width: f32,
+    pub paddle_speed: f32,
+    /// How far a double tap dashes the paddle
+    pub dash_distance: f32,
+    /// How long broken bricks and lost balls take to fade out, in seconds
+    pub fade_out_time: f32,
+    /// How long each part of the ball's trail lasts, in seconds
+    pub trail_fade_time: f32,
+    /// How long the countdown before play starts is, in seconds
+    pub countdown_time: f32,
+    /// The most a combo can multiply a brick's points by
+    pub max_combo_multiplier: usize,
+    pub time_bonus: TimeBonus,
+    /// How many balls a level can be played with, counting the first
+    pub lives: usize,
+}
+
+/// The tuning as the file has it, or the defaults without one
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CustomTuning(pub Tuning);
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            ball_speed: 400.0,
+            brick_speed_up: 30.0,
+            wall_slow_down: 20.0,
+            min_ball_speed: 100.0,
+            max_paddle_angle: 85.0,
+            paddle_width: 120.0,
+            paddle_speed: 500.0,
+            dash_distance: 180.0,
+            fade_out_time: 2.0,
+            trail_fade_time: 1.0,
+            countdown_time: 3.0,
+            max_combo_multiplier: 5,
+            time_bonus: TimeBonus {
+                max: 100,
+                curve: BonusCurve::Linear { zero_at: 120.0 },
+            },
+            lives: 3,
+        }
+    }
+}
+
+impl Tuning {
+    /// Read the tuning from `path`, falling back on the defaults if it can't be read
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => match ron::de::from_str(&contents) {
+                Ok(tuning) => tuning,
+                Err(e) => {
+                    println!("could not read {}, using the default tuning: {}", path, e);
+                    Self::default()
+                }
+            },
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// Where the tuning was loaded from, and when the file was last changed
+struct TuningFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    since_check: f32,
+}
+
+impl TuningFile {
+    fn new(path: PathBuf) -> Self {
+        let modified = Self::modified(&path);
+        Self {
+            path,
+            modified,
+            since_check: 0.0,
+        }
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+}
+
+/// Reloads the tuning when the file changes, unless a replay is playing back its own
+fn tuning_reload_system(
+    time: Res<Time>,
+    replay: Res<Replay>,
+    mut file: ResMut<TuningFile>,
+    mut custom: ResMut<CustomTuning>,
+) {
+    if let Replay::Playback(_) = *replay {
+        return;
+    }
+    file.since_check += time.delta_seconds;
+    if file.since_check < CHECK_INTERVAL {
+        return;
+    }
+    file.since_check = 0.0;
+    let modified = TuningFile::modified(&file.path);
+    if modified == file.modified {
+        return;
+    }
+    file.modified = modified;
+    let contents = match fs::read_to_string(&file.path) {
+        Ok(contents) => contents,
+        // deleted, or halfway through being saved
+        Err(_) => return,
+    };
+    match ron::de::from_str::<Tuning>(&contents) {
+        Ok(new_tuning) => {
+            if new_tuning != custom.0 {
+                println!("reloaded {}", file.path.display());
+                custom.0 = new_tuning;
+            }
+        }
+        Err(e) => println!(
+            "could not read {}, keeping the current tuning: {}",
+            file.path.display(),
+            e
+        ),
+    }
+}
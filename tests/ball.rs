@@ -0,0 +1,214 @@
+mod common;
+
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use common::{assert_near, Game};
+use my_bevy_game::{
+    ball::{Ball, Spin},
+    bricks::BrickType,
+    collision::{Bounce, Collider, Points},
+    game::FadeOut,
+    state::GameState,
+};
+
+#[test]
+fn moves_in_a_straight_line() {
+    let mut game = Game::playing();
+    game.ball(Vec2::zero(), Vec2::new(60.0, -120.0));
+    game.steps(3);
+    let (position, velocity) = game.balls()[0];
+    assert_near(position.x(), 3.0);
+    assert_near(position.y(), -6.0);
+    assert_eq!(velocity, Vec3::new(60.0, -120.0, 0.0));
+}
+
+#[test]
+fn bounces_off_a_side_wall_and_slows_down() {
+    let mut game = Game::playing();
+    game.wall(Vec2::new(40.0, 0.0), Vec2::new(10.0, 100.0));
+    game.ball(Vec2::zero(), Vec2::new(1800.0, 0.0));
+    game.step();
+    let (position, velocity) = game.balls()[0];
+    assert_near(velocity.x(), -1780.0);
+    assert_near(velocity.y(), 0.0);
+    assert!(position.x() < 20.0);
+}
+
+#[test]
+fn never_slows_below_the_minimum_speed() {
+    let mut game = Game::playing();
+    game.wall(Vec2::new(40.0, 0.0), Vec2::new(10.0, 100.0));
+    game.ball(Vec2::new(19.0, 0.0), Vec2::new(110.0, 0.0));
+    game.step();
+    assert_near(game.balls()[0].1.x(), -100.0);
+}
+
+#[test]
+fn breaking_a_brick_scores_and_speeds_up() {
+    let mut game = Game::playing();
+    game.brick(Vec2::new(0.0, 100.0), BrickType::Plain);
+    game.ball(Vec2::new(0.0, 60.0), Vec2::new(0.0, 1200.0));
+    game.step();
+    assert_eq!(game.score(), 1);
+    assert_eq!(game.combo(), 1);
+    assert_eq!(game.bricks_standing(), 1);
+    assert_eq!(game.count::<FadeOut>(), 1);
+    assert_near(game.balls()[0].1.y(), -1230.0);
+}
+
+#[test]
+fn a_tuning_change_applies_to_the_next_hit() {
+    let mut game = Game::playing();
+    game.tune(|tuning| {
+        tuning.brick_speed_up = 100.0;
+        tuning.max_combo_multiplier = 1;
+    });
+    game.set_combo(2);
+    game.brick(Vec2::new(0.0, 100.0), BrickType::Gold);
+    game.ball(Vec2::new(0.0, 60.0), Vec2::new(0.0, 1200.0));
+    game.step();
+    assert_eq!(game.score(), 3);
+    assert_near(game.balls()[0].1.y(), -1300.0);
+}
+
+#[test]
+fn brick_points_are_multiplied_by_the_combo() {
+    let mut game = Game::playing();
+    game.set_combo(2);
+    game.brick(Vec2::new(0.0, 100.0), BrickType::Gold);
+    game.ball(Vec2::new(0.0, 60.0), Vec2::new(0.0, 1200.0));
+    game.step();
+    assert_eq!(game.combo(), 3);
+    assert_eq!(game.score(), 3 * 3);
+}
+
+#[test]
+fn a_combo_cap_of_zero_still_scores_bricks() {
+    let mut game = Game::playing();
+    game.tune(|tuning| tuning.max_combo_multiplier = 0);
+    game.brick(Vec2::new(0.0, 100.0), BrickType::Gold);
+    game.ball(Vec2::new(0.0, 60.0), Vec2::new(0.0, 1200.0));
+    game.step();
+    assert_eq!(game.score(), 3);
+}
+
+#[test]
+fn a_brick_hit_by_two_balls_at_once_scores_once() {
+    let mut game = Game::playing();
+    game.brick(Vec2::new(0.0, 100.0), BrickType::Plain);
+    game.ball(Vec2::new(-30.0, 60.0), Vec2::new(0.0, 1200.0));
+    game.ball(Vec2::new(30.0, 60.0), Vec2::new(0.0, 1200.0));
+    game.step();
+    assert_eq!(game.score(), 1);
+    assert_eq!(game.bricks_standing(), 1);
+    // both balls still bounce off it
+    for (_position, velocity) in game.balls() {
+        assert!(velocity.y() < 0.0);
+    }
+    // and nothing goes wrong once the brick is gone
+    game.steps(2);
+}
+
+#[test]
+fn a_collider_that_is_not_destructible_scores_every_hit() {
+    let mut game = Game::playing();
+    // a bumper between two walls
+    game.spawn(|commands, materials, _pool| {
+        commands
+            .spawn(SpriteComponents {
+                material: materials.add(Color::WHITE.into()),
+                transform: Transform::from_translation(Vec3::new(0.0, 100.0, 0.0)),
+                sprite: Sprite::new(Vec2::new(150.0, 30.0)),
+                ..Default::default()
+            })
+            .with(Collider)
+            .with(Bounce::Reflect)
+            .with(Points(5));
+    });
+    game.wall(Vec2::new(0.0, 2.0), Vec2::new(150.0, 10.0));
+    game.ball(Vec2::new(0.0, 60.0), Vec2::new(0.0, 1200.0));
+    game.step();
+    assert_eq!(game.score(), 5);
+    // back down to the wall and up again
+    game.steps(6);
+    assert_eq!(game.score(), 5 + 5 * 2);
+    assert_eq!(game.combo(), 2);
+}
+
+#[test]
+fn breaking_the_last_brick_wins() {
+    let mut game = Game::new();
+    game.set_state(GameState::Playing);
+    game.brick(Vec2::new(0.0, 100.0), BrickType::Plain);
+    game.ball(Vec2::new(0.0, 60.0), Vec2::new(0.0, 1200.0));
+    game.steps(3);
+    assert_eq!(game.state(), GameState::Win);
+}
+
+#[test]
+fn losing_the_last_ball_loses() {
+    let mut game = Game::playing();
+    game.bottom_wall(Vec2::new(0.0, -300.0), Vec2::new(900.0, 10.0));
+    game.ball(Vec2::new(0.0, -265.0), Vec2::new(0.0, -1200.0));
+    game.step();
+    assert_eq!(game.count::<Ball>(), 0);
+    // the ball fades out where it fell
+    assert_eq!(game.count::<FadeOut>(), 1);
+    game.step();
+    assert_eq!(game.state(), GameState::Lose);
+}
+
+#[test]
+fn losing_the_last_ball_with_a_life_left_serves_another() {
+    let mut game = Game::playing();
+    game.set_lives(1);
+    game.bottom_wall(Vec2::new(0.0, -300.0), Vec2::new(900.0, 10.0));
+    game.ball(Vec2::new(0.0, -265.0), Vec2::new(0.0, -1200.0));
+    game.step();
+    assert_eq!(game.lives(), 0);
+    assert_eq!(game.count::<Ball>(), 1);
+    assert_eq!(game.count::<FadeOut>(), 1);
+    game.step();
+    assert_eq!(game.state(), GameState::Countdown);
+}
+
+#[test]
+fn losing_one_of_two_balls_carries_on() {
+    let mut game = Game::playing();
+    game.bottom_wall(Vec2::new(0.0, -300.0), Vec2::new(900.0, 10.0));
+    game.ball(Vec2::new(0.0, -265.0), Vec2::new(0.0, -1200.0));
+    game.ball(Vec2::new(0.0, 0.0), Vec2::new(0.0, 60.0));
+    game.steps(2);
+    assert_eq!(game.count::<Ball>(), 1);
+    assert_eq!(game.state(), GameState::Playing);
+}
+
+#[test]
+fn the_paddle_angles_the_bounce_by_where_it_was_hit() {
+    let mut game = Game::playing();
+    game.set_combo(4);
+    game.paddle(0.0);
+    game.ball(Vec2::new(30.0, -170.0), Vec2::new(0.0, -1200.0));
+    game.step();
+    let ball = game.the_ball();
+    // hitting 30 out of a possible 75 away from the middle bounces at 30/75 of 85 degrees
+    let angle = 30.0 / 75.0 * 85.0 * PI / 180.0;
+    assert_near(ball.velocity.x(), 1200.0 * angle.sin());
+    assert_near(ball.velocity.y(), 1200.0 * angle.cos());
+    assert_eq!(ball.spin, Spin::Clockwise);
+    assert_near(ball.last_paddle_offset, 30.0);
+    assert_eq!(game.combo(), 0);
+}
+
+#[test]
+fn the_left_of_the_paddle_spins_the_ball_the_other_way() {
+    let mut game = Game::playing();
+    game.paddle(0.0);
+    game.ball(Vec2::new(-30.0, -170.0), Vec2::new(0.0, -1200.0));
+    game.step();
+    let ball = game.the_ball();
+    assert!(ball.velocity.x() < 0.0);
+    assert_eq!(ball.spin, Spin::CounterCw);
+}
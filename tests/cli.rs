@@ -0,0 +1,96 @@
+use my_bevy_game::{bricks::Levels, cli::Args, difficulty::Difficulty};
+
+fn parse(args: &[&str]) -> Result<Args, String> {
+    Args::parse(
+        std::iter::once("my_bevy_game")
+            .chain(args.iter().copied())
+            .map(String::from),
+    )
+}
+
+#[test]
+fn no_options_is_the_default() {
+    assert_eq!(parse(&[]), Ok(Args::default()));
+}
+
+#[test]
+fn reads_every_option() {
+    let args = parse(&[
+        "--window-size",
+        "1280x720",
+        "--fullscreen",
+        "--level",
+        "Wall",
+        "--level-file",
+        "levels.ron",
+        "--seed",
+        "42",
+        "--difficulty",
+        "hard",
+        "--debug",
+        "--debug-draw",
+        "--headless",
+        "--replay",
+        "run.replay",
+        "--record",
+        "next.replay",
+    ])
+    .unwrap();
+    assert_eq!(args.window_size, Some((1280, 720)));
+    assert!(args.fullscreen);
+    assert_eq!(args.level.as_deref(), Some("Wall"));
+    assert_eq!(args.level_file, Some("levels.ron".into()));
+    assert_eq!(args.seed, Some(42));
+    assert_eq!(args.difficulty, Difficulty::Hard);
+    assert!(args.debug);
+    assert!(args.debug_draw);
+    assert!(args.headless);
+    assert_eq!(args.replay, Some("run.replay".into()));
+    assert_eq!(args.record, std::path::PathBuf::from("next.replay"));
+    assert!(!args.help);
+}
+
+#[test]
+fn rejects_bad_options() {
+    assert!(parse(&["--window-size", "1280"]).is_err());
+    assert!(parse(&["--seed", "minus one"]).is_err());
+    assert!(parse(&["--seed"]).is_err());
+    assert!(parse(&["--difficulty", "nightmare"]).is_err());
+    assert!(parse(&["--unknown"]).is_err());
+}
+
+#[test]
+fn finds_levels_by_number_or_name() {
+    let levels = Levels::default();
+    assert_eq!(levels.find("1"), Some(0));
+    assert_eq!(levels.find("3"), Some(2));
+    assert_eq!(levels.find("classic"), Some(1));
+    assert_eq!(levels.find("0"), None);
+    assert_eq!(levels.find("4"), None);
+    assert_eq!(levels.find("Nowhere"), None);
+}
+
+#[test]
+fn rejects_a_level_file_whose_bricks_dont_fit() {
+    let path = std::env::temp_dir().join("bevy-breakout-tall-level.ron");
+    std::fs::write(
+        &path,
+        r#"[(
+            name: "Tall",
+            brick_rows: 5,
+            brick_columns: 5,
+            bricks_bottom: 100.0,
+            arena: (width: 700.0, height: 500.0, wall_thickness: 20.0),
+        )]"#,
+    )
+    .unwrap();
+    assert!(Levels::load(&path).is_err());
+
+    std::fs::write(
+        &path,
+        r#"[(name: "Short", brick_rows: 2, brick_columns: 5, bricks_bottom: 100.0)]"#,
+    )
+    .unwrap();
+    assert!(Levels::load(&path).is_ok());
+    std::fs::remove_file(&path).unwrap();
+}
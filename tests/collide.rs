@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+
+use my_bevy_game::collision::{collide, CollisionX, CollisionY};
+
+const BALL_SIZE: (f32, f32) = (30.0, 30.0);
+
+fn ball_size() -> Vec2 {
+    Vec2::new(BALL_SIZE.0, BALL_SIZE.1)
+}
+
+#[test]
+fn hits_the_left_side_when_moving_right() {
+    let collision = collide(
+        Vec3::zero(),
+        ball_size(),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec2::new(10.0, 100.0),
+        &Vec3::new(1800.0, 0.0, 0.0),
+        1.0 / 60.0,
+    )
+    .expect("the ball should hit the wall");
+    assert_eq!(collision.x, (CollisionX::Left, 20.0));
+    assert_eq!(collision.y.0, CollisionY::None);
+}
+
+#[test]
+fn hits_the_top_when_falling() {
+    let collision = collide(
+        Vec3::new(0.0, 40.0, 0.0),
+        ball_size(),
+        Vec3::zero(),
+        Vec2::new(100.0, 10.0),
+        &Vec3::new(0.0, -1800.0, 0.0),
+        1.0 / 60.0,
+    )
+    .expect("the ball should land on the paddle");
+    assert_eq!(collision.x.0, CollisionX::None);
+    assert_eq!(collision.y, (CollisionY::Top, 20.0));
+}
+
+#[test]
+fn misses_when_it_does_not_reach() {
+    let collision = collide(
+        Vec3::zero(),
+        ball_size(),
+        Vec3::new(100.0, 0.0, 0.0),
+        Vec2::new(10.0, 100.0),
+        &Vec3::new(600.0, 0.0, 0.0),
+        1.0 / 60.0,
+    );
+    assert!(collision.is_none());
+}
+
+#[test]
+fn misses_when_moving_away() {
+    let collision = collide(
+        Vec3::zero(),
+        ball_size(),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec2::new(10.0, 100.0),
+        &Vec3::new(-1800.0, 0.0, 0.0),
+        1.0 / 60.0,
+    );
+    assert!(collision.is_none());
+}
+
+#[test]
+fn overlapping_has_no_side() {
+    let collision = collide(
+        Vec3::zero(),
+        ball_size(),
+        Vec3::new(10.0, 0.0, 0.0),
+        Vec2::new(10.0, 100.0),
+        &Vec3::zero(),
+        1.0 / 60.0,
+    )
+    .expect("the ball is already inside");
+    assert_eq!(collision.x.0, CollisionX::None);
+    assert_eq!(collision.y.0, CollisionY::None);
+}
+
+#[test]
+fn corner_hits_both_sides() {
+    let collision = collide(
+        Vec3::new(-40.0, -40.0, 0.0),
+        ball_size(),
+        Vec3::zero(),
+        Vec2::new(20.0, 20.0),
+        &Vec3::new(1200.0, 1200.0, 0.0),
+        1.0 / 60.0,
+    )
+    .expect("the ball should hit the corner");
+    assert_eq!(collision.x, (CollisionX::Left, -25.0));
+    assert_eq!(collision.y, (CollisionY::Bottom, -25.0));
+}
+
+#[test]
+fn normal_points_back_at_the_ball() {
+    let collision = collide(
+        Vec3::zero(),
+        ball_size(),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec2::new(10.0, 100.0),
+        &Vec3::new(1800.0, 0.0, 0.0),
+        1.0 / 60.0,
+    )
+    .unwrap();
+    assert_eq!(collision.normal(), Vec2::new(-1.0, 0.0));
+
+    let collision = collide(
+        Vec3::new(0.0, 40.0, 0.0),
+        ball_size(),
+        Vec3::zero(),
+        Vec2::new(100.0, 10.0),
+        &Vec3::new(0.0, -1800.0, 0.0),
+        1.0 / 60.0,
+    )
+    .unwrap();
+    assert_eq!(collision.normal(), Vec2::new(0.0, 1.0));
+}
+
+#[test]
+fn time_of_impact_is_the_fraction_of_the_frame_before_touching() {
+    let velocity = Vec3::new(1800.0, 0.0, 0.0);
+    let collision = collide(
+        Vec3::zero(),
+        ball_size(),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec2::new(10.0, 100.0),
+        &velocity,
+        1.0 / 60.0,
+    )
+    .unwrap();
+    // it has 20 to go out of the 30 it moves this frame
+    let time_of_impact = collision.time_of_impact(Vec3::zero(), &velocity, 1.0 / 60.0);
+    assert!((time_of_impact - 2.0 / 3.0).abs() < 1e-5);
+}
+
+#[test]
+fn time_of_impact_is_zero_when_already_overlapping() {
+    let collision = collide(
+        Vec3::zero(),
+        ball_size(),
+        Vec3::new(10.0, 0.0, 0.0),
+        Vec2::new(10.0, 100.0),
+        &Vec3::zero(),
+        1.0 / 60.0,
+    )
+    .unwrap();
+    assert_eq!(
+        collision.time_of_impact(Vec3::zero(), &Vec3::zero(), 1.0 / 60.0),
+        0.0
+    );
+}
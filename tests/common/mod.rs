@@ -0,0 +1,374 @@
+//! A headless game for the tests to set up exactly and then step one tick at a time.
+
+// each test file uses a different part of the harness
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use my_bevy_game::{
+    arena::{spawn_bottom_wall, spawn_wall, Arena},
+    ball::{spawn_ball, Ball, BallPlugin, Spin},
+    bricks::{spawn_brick, Brick, BrickType, BricksPlugin, Levels},
+    despawn::Despawns,
+    difficulty::{Difficulty, GameDifficulty},
+    game::{GamePlugin, GameRng, Lives, Options},
+    headless::HeadlessPlugin,
+    materials::MaterialPool,
+    paddle::{spawn_paddle, Paddle, PaddlePlugin},
+    scoring::{Combo, Scoreboard, ScoringPlugin},
+    state::{GameState, StateMachine},
+    tuning::{CustomTuning, Tuning},
+};
+
+/// The length of every tick, whatever the real time between them
+pub const TICK: f32 = 1.0 / 60.0;
+
+pub const BRICK_SIZE: (f32, f32) = (150.0, 30.0);
+
+pub struct Game {
+    app: App,
+}
+
+/// Replaces the real frame time, so that every tick is exactly [`TICK`] long
+fn fixed_tick_system(mut time: ResMut<Time>) {
+    time.delta_seconds = TICK;
+    time.delta_seconds_f64 = TICK as f64;
+    time.delta = Duration::from_secs_f32(TICK);
+}
+
+impl Game {
+    /// The gameplay plugins without the arena, menus or HUD, on the title screen with nothing
+    /// spawned. The ball trail is turned off, so the only things fading out are what was hit.
+    pub fn new() -> Self {
+        Self::with_seed(0)
+    }
+
+    /// As [`Game::new`], with the brick colours drawn from `seed`
+    pub fn with_seed(seed: u64) -> Self {
+        let mut builder = App::build();
+        builder
+            .add_plugin(HeadlessPlugin)
+            .add_plugin(GamePlugin { seed })
+            .add_plugin(ScoringPlugin)
+            .add_plugin(BallPlugin)
+            .add_plugin(PaddlePlugin)
+            .add_plugin(BricksPlugin)
+            .add_system_to_stage(stage::PRE_UPDATE, fixed_tick_system.system());
+        let app = std::mem::take(&mut builder.app);
+        app.resources.get_mut::<Options>().unwrap().ball_trail = false;
+        Self { app }
+    }
+
+    /// A game in [`GameState::Playing`], with a brick out of the ball's reach so that the level
+    /// isn't won before the test gets going
+    pub fn playing() -> Self {
+        let mut game = Self::new();
+        game.set_state(GameState::Playing);
+        game.brick(Vec2::new(0.0, 10_000.0), BrickType::Plain);
+        game
+    }
+
+    /// Jump straight to `state`, without running any of the enter or exit hooks
+    pub fn set_state(&mut self, state: GameState) {
+        *self.app.resources.get_mut::<StateMachine>().unwrap() = StateMachine::new(state);
+    }
+
+    /// Ask for a transition to `state`, which happens (and runs its hooks) on the next step
+    pub fn request_state(&mut self, state: GameState) {
+        self.app
+            .resources
+            .get_mut::<StateMachine>()
+            .unwrap()
+            .set(state);
+    }
+
+    pub fn state(&self) -> GameState {
+        self.app.resources.get::<StateMachine>().unwrap().current()
+    }
+
+    pub fn step(&mut self) {
+        self.app.update();
+    }
+
+    pub fn steps(&mut self, ticks: usize) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    /// Spawn whatever `spawn` spawns, straight away
+    pub fn spawn(
+        &mut self,
+        spawn: impl FnOnce(&mut Commands, &mut Assets<ColorMaterial>, &mut MaterialPool),
+    ) {
+        let mut commands = Commands::default();
+        {
+            let mut materials = self
+                .app
+                .resources
+                .get_mut::<Assets<ColorMaterial>>()
+                .unwrap();
+            let mut pool = self.app.resources.get_mut::<MaterialPool>().unwrap();
+            spawn(&mut commands, &mut materials, &mut pool);
+        }
+        commands.apply(&mut self.app.world, &mut self.app.resources);
+    }
+
+    pub fn ball(&mut self, position: Vec2, velocity: Vec2) {
+        self.spawn(|commands, materials, _pool| {
+            spawn_ball(
+                commands,
+                materials,
+                position.extend(10.0),
+                Ball {
+                    velocity: velocity.extend(0.0),
+                    rotation: 0.0,
+                    rotational_velocity: 0.0,
+                    spin: Spin::Clockwise,
+                    last_paddle_offset: 0.0,
+                },
+                Color::WHITE,
+            )
+        });
+    }
+
+    pub fn paddle(&mut self, x: f32) {
+        self.spawn(|commands, materials, _pool| {
+            spawn_paddle(
+                commands,
+                materials,
+                Vec2::new(x, Arena::default().paddle_y()),
+                120.0,
+                Color::BLACK,
+            )
+        });
+    }
+
+    pub fn brick(&mut self, position: Vec2, brick_type: BrickType) {
+        self.spawn(|commands, materials, pool| {
+            spawn_brick(
+                commands,
+                materials,
+                pool,
+                position.extend(0.0),
+                Vec2::new(BRICK_SIZE.0, BRICK_SIZE.1),
+                Color::WHITE,
+                brick_type,
+                format!("Brick at {}, {}", position.x(), position.y()),
+            )
+        });
+    }
+
+    pub fn wall(&mut self, position: Vec2, size: Vec2) {
+        self.spawn(|commands, materials, _pool| {
+            let material = materials.add(Color::rgb(0.5, 0.5, 0.5).into());
+            spawn_wall(commands, material, position.extend(0.0), size, "Wall");
+        });
+    }
+
+    pub fn bottom_wall(&mut self, position: Vec2, size: Vec2) {
+        self.spawn(|commands, materials, _pool| {
+            let material = materials.add(Color::rgb(0.5, 0.5, 0.5).into());
+            spawn_bottom_wall(commands, material, position.extend(0.0), size);
+        });
+    }
+
+    /// The position and velocity of every ball still in play, left to right
+    pub fn balls(&self) -> Vec<(Vec3, Vec3)> {
+        let mut balls: Vec<(Vec3, Vec3)> = self
+            .app
+            .world
+            .query::<(&Ball, &Transform)>()
+            .iter()
+            .map(|(ball, transform)| (transform.translation, ball.velocity))
+            .collect();
+        balls.sort_by(|a, b| a.0.x().partial_cmp(&b.0.x()).unwrap());
+        balls
+    }
+
+    /// The only ball in play
+    pub fn the_ball(&self) -> Ball {
+        let mut query = self.app.world.query::<&Ball>();
+        let mut balls = query.iter();
+        let ball = balls.next().expect("there is no ball");
+        assert!(balls.next().is_none(), "there is more than one ball");
+        Ball {
+            velocity: ball.velocity,
+            rotation: ball.rotation,
+            rotational_velocity: ball.rotational_velocity,
+            spin: ball.spin,
+            last_paddle_offset: ball.last_paddle_offset,
+        }
+    }
+
+    pub fn entities<T: Component>(&self) -> Vec<Entity> {
+        self.app
+            .world
+            .query::<(Entity, &T)>()
+            .iter()
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    /// Ask for `entity` to be despawned at the end of the next step
+    pub fn despawn(&mut self, entity: Entity) {
+        self.app
+            .resources
+            .get_mut::<Despawns>()
+            .unwrap()
+            .despawn(entity);
+    }
+
+    /// How many materials the pool is holding on to, see [`MaterialPool::counts`]
+    pub fn pool_counts(&self) -> (usize, usize, usize) {
+        self.app.resources.get::<MaterialPool>().unwrap().counts()
+    }
+
+    pub fn count<T: Component>(&self) -> usize {
+        self.app.world.query::<&T>().iter().count()
+    }
+
+    /// Where the paddle is
+    pub fn paddle_position(&self) -> Vec3 {
+        self.app
+            .world
+            .query::<(&Paddle, &Transform)>()
+            .iter()
+            .next()
+            .map(|(_paddle, transform)| transform.translation)
+            .expect("there is no paddle")
+    }
+
+    /// Play `levels` in place of the built-in ones, from the next restart
+    pub fn set_levels(&mut self, levels: Levels) {
+        *self.app.resources.get_mut::<Levels>().unwrap() = levels;
+    }
+
+    pub fn arena(&self) -> Arena {
+        self.app.resources.get::<Arena>().unwrap().clone()
+    }
+
+    /// Where each brick is and its size
+    pub fn brick_rects(&self) -> Vec<(Vec3, Vec2)> {
+        self.app
+            .world
+            .query::<(&Brick, &Transform, &Sprite)>()
+            .iter()
+            .map(|(_brick, transform, sprite)| (transform.translation, sprite.size))
+            .collect()
+    }
+
+    /// How many bricks haven't been broken yet
+    pub fn bricks_standing(&self) -> usize {
+        self.app
+            .world
+            .query::<&Brick>()
+            .iter()
+            .filter(|brick| brick.0)
+            .count()
+    }
+
+    /// The seed of the game in progress, as shown at the end of it
+    pub fn seed(&self) -> u64 {
+        self.app.resources.get::<GameRng>().unwrap().seed
+    }
+
+    /// Where each brick is and its colour, bottom to top and left to right
+    pub fn brick_layout(&self) -> Vec<(Vec3, Color)> {
+        let materials = self.app.resources.get::<Assets<ColorMaterial>>().unwrap();
+        let mut bricks: Vec<(Vec3, Color)> = self
+            .app
+            .world
+            .query::<(&Brick, &Transform, &Handle<ColorMaterial>)>()
+            .iter()
+            .map(|(_brick, transform, material)| {
+                (
+                    transform.translation,
+                    materials.get(material).unwrap().color,
+                )
+            })
+            .collect();
+        bricks.sort_by(|a, b| (a.0.y(), a.0.x()).partial_cmp(&(b.0.y(), b.0.x())).unwrap());
+        bricks
+    }
+
+    pub fn set_ball_trail(&mut self, on: bool) {
+        self.app.resources.get_mut::<Options>().unwrap().ball_trail = on;
+    }
+
+    /// Change the tuning file and switch to the Custom difficulty, so that all of it is used from
+    /// the next step
+    pub fn tune(&mut self, change: impl FnOnce(&mut Tuning)) {
+        change(&mut self.app.resources.get_mut::<CustomTuning>().unwrap().0);
+        self.set_difficulty(Difficulty::Custom);
+    }
+
+    /// Change the difficulty, which applies from the next step
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        *self.app.resources.get_mut::<Difficulty>().unwrap() = difficulty;
+    }
+
+    /// The difficulty the game in progress is played and scored at
+    pub fn game_difficulty(&self) -> Difficulty {
+        self.app.resources.get::<GameDifficulty>().unwrap().0
+    }
+
+    pub fn tuning(&self) -> Tuning {
+        self.app.resources.get::<Tuning>().unwrap().clone()
+    }
+
+    pub fn lives(&self) -> usize {
+        self.app.resources.get::<Lives>().unwrap().remaining
+    }
+
+    pub fn set_lives(&mut self, remaining: usize) {
+        self.app.resources.get_mut::<Lives>().unwrap().remaining = remaining;
+    }
+
+    /// How many `ColorMaterial`s exist
+    pub fn material_count(&self) -> usize {
+        self.app
+            .resources
+            .get::<Assets<ColorMaterial>>()
+            .unwrap()
+            .iter()
+            .count()
+    }
+
+    /// The material of each brick
+    pub fn brick_materials(&self) -> Vec<Handle<ColorMaterial>> {
+        self.app
+            .world
+            .query::<(&Brick, &Handle<ColorMaterial>)>()
+            .iter()
+            .map(|(_brick, material)| material.clone())
+            .collect()
+    }
+
+    pub fn score(&self) -> usize {
+        self.app.resources.get::<Scoreboard>().unwrap().score
+    }
+
+    pub fn set_score(&mut self, score: usize) {
+        self.app.resources.get_mut::<Scoreboard>().unwrap().score = score;
+    }
+
+    pub fn combo(&self) -> usize {
+        self.app.resources.get::<Combo>().unwrap().hits
+    }
+
+    pub fn set_combo(&mut self, hits: usize) {
+        self.app.resources.get_mut::<Combo>().unwrap().hits = hits;
+    }
+}
+
+pub fn assert_near(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1e-3,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::Game;
+use my_bevy_game::{
+    bricks::{Brick, BrickType},
+    game::FadeOut,
+    materials::Pooled,
+    state::GameState,
+};
+
+#[test]
+fn despawning_twice_in_one_frame_despawns_once() {
+    let mut game = Game::playing();
+    game.brick(Vec2::new(0.0, 100.0), BrickType::Plain);
+    let entities = game.entities::<Brick>();
+    assert_eq!(entities.len(), 2);
+    game.despawn(entities[0]);
+    game.despawn(entities[0]);
+    game.step();
+    assert_eq!(game.count::<Brick>(), 1);
+}
+
+#[test]
+fn despawning_something_already_gone_does_nothing() {
+    let mut game = Game::playing();
+    game.brick(Vec2::new(0.0, 100.0), BrickType::Plain);
+    let brick = game.entities::<Brick>()[0];
+    game.despawn(brick);
+    game.step();
+    game.despawn(brick);
+    game.step();
+    assert_eq!(game.count::<Brick>(), 1);
+}
+
+#[test]
+fn a_faded_out_brick_gives_its_material_back() {
+    let mut game = Game::playing();
+    game.brick(Vec2::new(0.0, 100.0), BrickType::Plain);
+    game.ball(Vec2::new(0.0, 60.0), Vec2::new(0.0, 1200.0));
+    game.step();
+    assert_eq!(game.count::<FadeOut>(), 1);
+    game.step();
+    assert_eq!(game.count::<Pooled>(), 1);
+    assert_eq!(game.pool_counts().1, 1);
+    // longer than it takes to fade out
+    game.steps(150);
+    assert_eq!(game.count::<FadeOut>(), 0);
+    assert_eq!(game.pool_counts(), (1, 0, 1));
+}
+
+#[test]
+fn restarting_gives_back_the_materials_of_whatever_was_fading() {
+    let mut game = Game::playing();
+    game.set_ball_trail(true);
+    game.ball(Vec2::zero(), Vec2::new(0.0, 60.0));
+    game.steps(10);
+    let (_shared, taken, free) = game.pool_counts();
+    assert!(taken > 0);
+    game.request_state(GameState::Restarting);
+    game.step();
+    assert_eq!(game.count::<Pooled>(), 0);
+    assert_eq!(game.pool_counts(), (0, 0, taken + free));
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::Game;
+use my_bevy_game::{difficulty::Difficulty, state::GameState};
+
+#[test]
+fn normal_plays_with_the_default_tuning() {
+    let mut game = Game::new();
+    game.step();
+    assert_eq!(game.tuning(), Default::default());
+}
+
+#[test]
+fn a_preset_applies_from_the_next_step() {
+    let mut game = Game::new();
+    game.set_difficulty(Difficulty::Hard);
+    game.step();
+    let tuning = game.tuning();
+    assert_eq!(tuning.ball_speed, 500.0);
+    assert_eq!(tuning.min_ball_speed, 200.0);
+    assert_eq!(tuning.paddle_width, 90.0);
+    assert_eq!(tuning.lives, 1);
+}
+
+#[test]
+fn custom_plays_the_tuning_file_as_it_is() {
+    let mut game = Game::new();
+    game.tune(|tuning| {
+        tuning.ball_speed = 123.0;
+        tuning.lives = 9;
+    });
+    game.step();
+    assert_eq!(game.tuning().ball_speed, 123.0);
+    assert_eq!(game.tuning().lives, 9);
+}
+
+#[test]
+fn a_preset_leaves_the_rest_of_the_tuning_alone() {
+    let mut game = Game::new();
+    game.tune(|tuning| {
+        tuning.ball_speed = 123.0;
+        tuning.countdown_time = 1.0;
+    });
+    game.set_difficulty(Difficulty::Easy);
+    game.step();
+    assert_eq!(game.tuning().ball_speed, 300.0);
+    assert_eq!(game.tuning().countdown_time, 1.0);
+}
+
+#[test]
+fn the_difficulties_go_round_in_the_menu() {
+    let mut difficulty = Difficulty::Easy;
+    for _ in 0..Difficulty::ALL.len() {
+        difficulty = difficulty.next();
+    }
+    assert_eq!(difficulty, Difficulty::Easy);
+    assert_eq!(Difficulty::Hard.next(), Difficulty::Custom);
+}
+
+#[test]
+fn a_game_keeps_the_difficulty_it_started_at() {
+    let mut game = Game::new();
+    game.set_difficulty(Difficulty::Easy);
+    game.request_state(GameState::Restarting);
+    game.steps(2);
+    assert_eq!(game.state(), GameState::Starting);
+    // as if changed from the pause menu's options
+    game.set_difficulty(Difficulty::Hard);
+    game.step();
+    assert_eq!(game.game_difficulty(), Difficulty::Easy);
+    assert_eq!(game.tuning().lives, 5);
+
+    // the next game is played at the new one
+    game.request_state(GameState::Restarting);
+    game.steps(2);
+    assert_eq!(game.game_difficulty(), Difficulty::Hard);
+    assert_eq!(game.tuning().lives, 1);
+}
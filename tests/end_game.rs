@@ -0,0 +1,123 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::{assert_near, Game};
+use my_bevy_game::{
+    arena::Arena,
+    ball::Ball,
+    bricks::{Brick, BrickType, Levels},
+    paddle::Paddle,
+    state::GameState,
+};
+
+#[test]
+fn restarting_clears_the_last_game_and_spawns_the_first_level() {
+    let mut game = Game::playing();
+    game.paddle(100.0);
+    game.ball(Vec2::zero(), Vec2::new(0.0, 60.0));
+    game.set_score(12);
+    game.set_combo(3);
+    game.set_state(GameState::Lose);
+
+    game.request_state(GameState::Restarting);
+    game.step();
+    assert_eq!(game.state(), GameState::Restarting);
+    assert_eq!(game.count::<Ball>(), 0);
+    assert_eq!(game.count::<Brick>(), 0);
+    assert_eq!(game.count::<Paddle>(), 0);
+    assert_eq!(game.score(), 0);
+    assert_eq!(game.combo(), 0);
+
+    game.step();
+    assert_eq!(game.state(), GameState::Starting);
+    // the "Warm up" level is three rows of five
+    assert_eq!(game.bricks_standing(), 15);
+    assert_eq!(game.count::<Ball>(), 1);
+    assert_eq!(game.count::<Paddle>(), 1);
+}
+
+#[test]
+fn restarting_while_a_brick_fades_out() {
+    let mut game = Game::playing();
+    game.brick(Vec2::new(0.0, 100.0), BrickType::Plain);
+    game.ball(Vec2::new(0.0, 60.0), Vec2::new(0.0, 1200.0));
+    game.step();
+    assert_eq!(game.score(), 1);
+
+    // the broken brick is still fading out when the game is thrown away
+    game.request_state(GameState::Restarting);
+    game.steps(3);
+    assert_eq!(game.bricks_standing(), 15);
+}
+
+#[test]
+fn each_game_is_played_from_the_seed_it_shows() {
+    let mut game = Game::new();
+    game.request_state(GameState::Restarting);
+    game.steps(2);
+    // the first game is played from the seed the game was started with
+    assert_eq!(game.seed(), 0);
+    let first = game.brick_layout();
+
+    game.request_state(GameState::Restarting);
+    game.steps(2);
+    let seed = game.seed();
+    let second = game.brick_layout();
+    assert_ne!(seed, 0);
+    assert_ne!(second, first);
+
+    let mut repeat = Game::with_seed(seed);
+    repeat.request_state(GameState::Restarting);
+    repeat.steps(2);
+    assert_eq!(repeat.brick_layout(), second);
+}
+
+#[test]
+fn a_level_brings_its_own_arena() {
+    let arena = Arena {
+        width: 700.0,
+        height: 500.0,
+        wall_thickness: 20.0,
+    };
+    let mut levels = Levels::default();
+    levels.0[0].arena = arena.clone();
+    let mut game = Game::playing();
+    game.set_levels(levels);
+    game.request_state(GameState::Restarting);
+    game.steps(2);
+    assert_eq!(game.arena(), arena);
+    // a little way above the inside of the bottom wall
+    assert_near(game.paddle_position().y(), -240.0 + 80.0);
+}
+
+#[test]
+fn the_bricks_are_narrowed_to_fit_a_narrow_arena() {
+    let arena = Arena {
+        width: 700.0,
+        height: 500.0,
+        wall_thickness: 20.0,
+    };
+    let mut levels = Levels::default();
+    levels.0[0].arena = arena.clone();
+    let mut game = Game::playing();
+    game.set_levels(levels);
+    game.request_state(GameState::Restarting);
+    game.steps(2);
+    let bricks = game.brick_rects();
+    assert_eq!(bricks.len(), 15);
+    for (position, size) in bricks {
+        assert!(size.x() < 150.0);
+        assert!(position.x().abs() + size.x() / 2.0 < arena.inner_half_width());
+    }
+}
+
+#[test]
+fn the_bricks_keep_their_size_in_the_default_arena() {
+    let mut game = Game::playing();
+    game.request_state(GameState::Restarting);
+    game.steps(2);
+    for (_position, size) in game.brick_rects() {
+        assert_eq!(size, Vec2::new(150.0, 30.0));
+    }
+}
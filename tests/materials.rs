@@ -0,0 +1,32 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::Game;
+use my_bevy_game::{bricks::BrickType, materials::Pooled};
+
+#[test]
+fn the_ball_trail_recycles_its_materials() {
+    let mut game = Game::playing();
+    game.set_ball_trail(true);
+    game.ball(Vec2::zero(), Vec2::new(0.0, 60.0));
+    // long enough for the first trail sprites to have faded and been despawned
+    game.steps(120);
+    let materials = game.material_count();
+    let trail = game.count::<Pooled>();
+    game.steps(300);
+    assert_eq!(game.material_count(), materials);
+    assert_eq!(game.count::<Pooled>(), trail);
+}
+
+#[test]
+fn bricks_of_the_same_colour_share_a_material() {
+    let mut game = Game::new();
+    let materials = game.material_count();
+    game.brick(Vec2::new(-200.0, 100.0), BrickType::Plain);
+    game.brick(Vec2::new(200.0, 100.0), BrickType::Plain);
+    let bricks = game.brick_materials();
+    assert_eq!(bricks.len(), 2);
+    assert_eq!(bricks[0], bricks[1]);
+    assert_eq!(game.material_count(), materials + 1);
+}